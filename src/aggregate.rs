@@ -0,0 +1,171 @@
+#![allow(dead_code, private_bounds)]
+use crate::client::{Client, Portal, Query};
+use crate::common::{non_finite, quote_field, Field, Order};
+use crate::error::Error;
+use crate::expr::Expr;
+use crate::filter::Filter;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::marker::PhantomData;
+
+/// Highest number of groups the records endpoint returns for a `group_by` query.
+pub const MAX_GROUPS: u64 = 20_000;
+
+/// Server-side aggregation, rendered to the ODSQL `select` and `group_by` parameters.
+///
+/// Every aggregate is returned under its alias. Grouping keys are returned under the
+/// field name, or `year_<field>` and `month_<field>` for the date parts.
+#[derive(Debug, Clone, Default)]
+pub struct Aggregation<T: Field> {
+    pub(crate) select: Vec<String>,
+    pub(crate) group_by: Vec<String>,
+    pub(crate) keys: Vec<String>,
+    /// Percentile ODSQL has no literal for, see [`Error::InvalidValue`].
+    pub(crate) invalid: Option<String>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Field> Aggregation<T> {
+    pub fn new() -> Self {
+        Aggregation {
+            select: Vec::new(),
+            group_by: Vec::new(),
+            keys: Vec::new(),
+            invalid: None,
+            phantom: PhantomData,
+        }
+    }
+
+    fn aggregate(mut self, expression: String, alias: &str) -> Self {
+        self.select
+            .push(format!("{expression} as {}", quote_field(alias)));
+        self
+    }
+
+    fn group(mut self, expression: String, alias: String) -> Self {
+        self.group_by
+            .push(format!("{expression} as {}", quote_field(&alias)));
+        self.keys.push(alias);
+        self
+    }
+
+    pub fn count(self, alias: &str) -> Self {
+        self.aggregate("count(*)".to_string(), alias)
+    }
+
+    pub fn sum(self, field: T, alias: &str) -> Self {
+        self.aggregate(format!("sum({})", quote_field(field.name())), alias)
+    }
+
+    pub fn avg(self, field: T, alias: &str) -> Self {
+        self.aggregate(format!("avg({})", quote_field(field.name())), alias)
+    }
+
+    pub fn min(self, field: T, alias: &str) -> Self {
+        self.aggregate(format!("min({})", quote_field(field.name())), alias)
+    }
+
+    pub fn max(self, field: T, alias: &str) -> Self {
+        self.aggregate(format!("max({})", quote_field(field.name())), alias)
+    }
+
+    /// `percentile` is between 0 and 100, e.g. 50 for the median.
+    pub fn percentile(mut self, field: T, percentile: f64, alias: &str) -> Self {
+        let out_of_range = (!(0.0..=100.0).contains(&percentile)).then(|| percentile.to_string());
+        self.invalid = self.invalid.or(non_finite([percentile])).or(out_of_range);
+        self.aggregate(
+            format!("percentile({}, {percentile})", quote_field(field.name())),
+            alias,
+        )
+    }
+
+    pub fn group_by(self, field: T) -> Self {
+        let name = field.name();
+        self.group(quote_field(name), name.to_string())
+    }
+
+    pub fn group_by_year(self, field: T) -> Self {
+        let name = field.name();
+        self.group(
+            format!("year({})", quote_field(name)),
+            format!("year_{name}"),
+        )
+    }
+
+    pub fn group_by_month(self, field: T) -> Self {
+        let name = field.name();
+        self.group(
+            format!("month({})", quote_field(name)),
+            format!("month_{name}"),
+        )
+    }
+
+    /// Group by a computed value, returned under `alias`.
+    pub fn group_by_expr(self, expression: Expr<T>, alias: &str) -> Self {
+        self.group(expression.render(), alias.to_string())
+    }
+}
+
+/// One row of an aggregation, split into its grouping keys and aggregated values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub keys: Map<String, Value>,
+    pub values: Map<String, Value>,
+}
+
+impl Group {
+    /// Grouping key or aggregated value by its alias, deserialized into `V`.
+    pub fn get<V: DeserializeOwned>(&self, alias: &str) -> Option<V> {
+        let value = self.keys.get(alias).or_else(|| self.values.get(alias))?;
+        V::deserialize(value).ok()
+    }
+}
+
+#[derive(Deserialize)]
+struct Groups {
+    total_count: Option<u64>,
+    results: Vec<Map<String, Value>>,
+}
+
+impl Client {
+    pub(crate) async fn records_aggregate<F: Field>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        aggregation: Aggregation<F>,
+        order: Order<F>,
+        filter: Option<Filter<F>>,
+    ) -> Result<Vec<Group>, Error> {
+        let mut query = Query::new(order, filter);
+        query.select = aggregation.select.join(", ");
+        query.group_by = aggregation.group_by.join(", ");
+        query.invalid = query.invalid.or(aggregation.invalid);
+        let url = self.dataset_url(portal, dataset, "records")?;
+        let mut params = query.params()?;
+        // The records endpoint only accepts a limit above 100 for `group_by` queries.
+        if !aggregation.group_by.is_empty() {
+            params.push(("limit", MAX_GROUPS.to_string()));
+        }
+        let url = reqwest::Url::parse_with_params(&url, &params)?;
+        let groups: Groups = self.fetch(portal, dataset, url).await?;
+        if let Some(total_count) = groups.total_count {
+            if total_count > groups.results.len() as u64 {
+                return Err(Error::TooManyGroups { total_count });
+            }
+        }
+        let groups = groups
+            .results
+            .into_iter()
+            .map(|mut values| {
+                let keys = aggregation
+                    .keys
+                    .iter()
+                    .filter_map(|key| values.remove_entry(key))
+                    .collect();
+                Group { keys, values }
+            })
+            .collect();
+        Ok(groups)
+    }
+}
@@ -0,0 +1,29 @@
+//! Serve a directory of `.json`, `.jsonl` and `.csv` files as Opendatasoft datasets.
+//!
+//! Usage: `basel-mock <dir> [address]`, e.g. `basel-mock tests/data 127.0.0.1:8080`.
+use basel::testing::MockServer;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let Some(dir) = args.next() else {
+        eprintln!("usage: basel-mock <dir> [address]");
+        std::process::exit(2);
+    };
+    let address = args.next().unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let server = MockServer::bind(&address).and_then(|server| {
+        server.load_dir(&dir)?;
+        Ok(server)
+    });
+    match server {
+        Ok(server) => {
+            println!("serving {dir} at {}", server.url());
+            loop {
+                std::thread::park();
+            }
+        }
+        Err(error) => {
+            eprintln!("basel-mock: {error}");
+            std::process::exit(1);
+        }
+    }
+}
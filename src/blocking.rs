@@ -0,0 +1,216 @@
+//! Synchronous API for programs without an async runtime, like `reqwest::blocking`.
+//!
+//! Every dataset module has a `blocking` submodule with the same functions as the module
+//! itself, taking a [`blocking::Client`](Client) and returning results or iterators directly.
+//!
+//! ```no_run
+//! # #[cfg(feature = "bs100003")]
+//! # fn example() -> Result<(), basel::Error> {
+//! use basel::stadt::basler_index_der_konsumentenpreise::blocking;
+//! use basel::Order;
+//!
+//! let first_page = blocking::get(10, 0, Order::new(), None)?;
+//! for record in blocking::export(Order::new(), None) {
+//!     println!("{:?}", record?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The functions without a client share one, created on first use, so their requests reuse
+//! its runtime and connections. They must not be called from within an async runtime, where
+//! they panic.
+use crate::aggregate::{Aggregation, Group};
+use crate::common::{Data, Order, Refine};
+use crate::dataset::Dataset;
+use crate::error::Error;
+use crate::export::ExportFormat;
+use crate::facet::FacetValue;
+use crate::filter::Filter;
+use crate::metadata::Metadata;
+use crate::Portal;
+use bytes::Bytes;
+use futures_util::stream::BoxStream;
+use futures_util::{Stream, StreamExt};
+use std::future::Future;
+use std::sync::{Arc, OnceLock};
+use tokio::runtime::Runtime;
+
+/// Blocking counterpart of [`crate::Client`], running its requests on an internal runtime.
+///
+/// Cloning is cheap and shares the connection pool and the runtime.
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: crate::Client,
+    runtime: Arc<Runtime>,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client::new()
+    }
+}
+
+impl From<crate::Client> for Client {
+    /// Block on `client`, keeping its base URLs, retry policy, rate limiter and cache.
+    ///
+    /// The connection pool is shared with `client` as well. Connections are bound to the
+    /// runtime they were opened on and fail once it shuts down, so convert a client that has
+    /// not sent requests yet, or whose runtime outlives the blocking client.
+    fn from(client: crate::Client) -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to start the runtime of the blocking client");
+        Client {
+            inner: client,
+            runtime: Arc::new(runtime),
+        }
+    }
+}
+
+impl Client {
+    pub fn new() -> Self {
+        crate::Client::new().into()
+    }
+
+    /// The async client the requests are sent with.
+    pub fn inner(&self) -> &crate::Client {
+        &self.inner
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    fn iter<T>(&self, stream: impl Stream<Item = Result<T, Error>> + Send + 'static) -> Iter<T> {
+        Iter {
+            stream: stream.boxed(),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    pub fn get<D: Dataset>(
+        &self,
+        limit: u8,
+        offset: u64,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Result<Data<D::Record>, Error> {
+        self.block_on(self.inner.get::<D>(limit, offset, order, filter))
+    }
+
+    /// All records, fetched page by page as the iterator advances.
+    ///
+    /// If more than [`MAX_OFFSET`](crate::MAX_OFFSET) records match, the only item is
+    /// [`Error::OffsetLimit`], however few records are taken.
+    pub fn stream<D: Dataset>(
+        &self,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Iter<D::Record> {
+        self.iter(self.inner.records_stream(D::PORTAL, D::ID, order, filter))
+    }
+
+    /// All records from the exports endpoint, decoded as they arrive.
+    pub fn export<D: Dataset>(
+        &self,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Iter<D::Record> {
+        self.iter(self.inner.export_records(D::PORTAL, D::ID, order, filter))
+    }
+
+    pub fn export_bytes<D: Dataset>(
+        &self,
+        format: ExportFormat,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Iter<Bytes> {
+        self.iter(
+            self.inner
+                .export_raw(D::PORTAL, D::ID, format, order, filter),
+        )
+    }
+
+    pub fn select<D: Dataset>(
+        &self,
+        fields: &[D::Field],
+        limit: u8,
+        offset: u64,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Result<Data<D::Record>, Error> {
+        self.block_on(self.inner.select::<D>(fields, limit, offset, order, filter))
+    }
+
+    pub fn get_refined<D: Dataset>(
+        &self,
+        limit: u8,
+        offset: u64,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+        refine: Refine<D::Field>,
+    ) -> Result<Data<D::Record>, Error> {
+        self.block_on(
+            self.inner
+                .get_refined::<D>(limit, offset, order, filter, refine),
+        )
+    }
+
+    pub fn aggregate<D: Dataset>(
+        &self,
+        aggregation: Aggregation<D::Field>,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Result<Vec<Group>, Error> {
+        self.block_on(self.inner.aggregate::<D>(aggregation, order, filter))
+    }
+
+    pub fn facets<D: Dataset>(
+        &self,
+        field: D::Field,
+        refine: Refine<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Result<Vec<FacetValue>, Error> {
+        self.block_on(self.inner.facets::<D>(field, refine, filter))
+    }
+
+    pub fn metadata<D: Dataset>(&self) -> Result<Metadata, Error> {
+        self.block_on(self.inner.metadata::<D>())
+    }
+
+    /// Fetch the catalog entry of any dataset, compiled in or not.
+    pub fn dataset_metadata(&self, portal: Portal, dataset: &str) -> Result<Metadata, Error> {
+        self.block_on(self.inner.dataset_metadata(portal, dataset))
+    }
+}
+
+/// Client of the dataset functions without a `client` argument.
+///
+/// Only used by the generated modules, so unused without a dataset feature.
+#[allow(dead_code)]
+pub(crate) fn shared() -> &'static Client {
+    static SHARED: OnceLock<Client> = OnceLock::new();
+    SHARED.get_or_init(Client::new)
+}
+
+/// Iterator over a paginated or exported result, sending requests as it advances.
+pub struct Iter<T> {
+    stream: BoxStream<'static, Result<T, Error>>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T> Iterator for Iter<T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
+
+impl<T> std::fmt::Debug for Iter<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Iter").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,407 @@
+use crate::client::{Client, Portal};
+use crate::dataset::Dataset;
+use crate::error::Error;
+use bytes::Bytes;
+use reqwest::header::{
+    HeaderMap, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Storage for cached responses, keyed by the full request URL.
+///
+/// Caching is best effort: a backend that fails to store or load an entry simply behaves as
+/// if it was never cached.
+pub trait Cache: Debug + Send + Sync {
+    fn get(&self, key: &str) -> Option<CacheEntry>;
+    fn put(&self, key: &str, entry: CacheEntry);
+    fn remove(&self, key: &str);
+}
+
+/// A response body together with the validators needed to revalidate it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub body: Bytes,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// When the body was downloaded or last revalidated.
+    pub stored: SystemTime,
+}
+
+/// Cache keeping entries in memory for the lifetime of the process.
+///
+/// Holds at most [`MemoryCache::DEFAULT_CAPACITY`] entries unless configured otherwise, and
+/// evicts the least recently used entry to make room for a new one.
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+/// Entries of a [`MemoryCache`] together with the order they were last used in.
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<String, (CacheEntry, u64)>,
+    /// Keys by the tick of their last use, oldest first.
+    used: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl Entries {
+    fn touch(&mut self, key: &str) -> Option<&CacheEntry> {
+        self.tick += 1;
+        let (entry, used) = self.map.get_mut(key)?;
+        self.used.remove(used);
+        *used = self.tick;
+        self.used.insert(self.tick, key.to_string());
+        Some(entry)
+    }
+}
+
+impl Default for MemoryCache {
+    fn default() -> Self {
+        MemoryCache {
+            capacity: Self::DEFAULT_CAPACITY,
+            entries: Mutex::default(),
+        }
+    }
+}
+
+impl MemoryCache {
+    pub const DEFAULT_CAPACITY: usize = 1000;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep at most `capacity` entries, i.e. pages, aggregations, facets or metadata.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Cache for MemoryCache {
+    fn get(&self, key: &str) -> Option<CacheEntry> {
+        self.entries().touch(key).cloned()
+    }
+
+    fn put(&self, key: &str, entry: CacheEntry) {
+        let mut entries = self.entries();
+        entries.tick += 1;
+        let tick = entries.tick;
+        if let Some((_, used)) = entries.map.insert(key.to_string(), (entry, tick)) {
+            entries.used.remove(&used);
+        }
+        entries.used.insert(tick, key.to_string());
+        while entries.map.len() > self.capacity {
+            let Some((_, oldest)) = entries.used.pop_first() else {
+                break;
+            };
+            entries.map.remove(&oldest);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let mut entries = self.entries();
+        if let Some((_, used)) = entries.map.remove(key) {
+            entries.used.remove(&used);
+        }
+    }
+}
+
+/// Cache keeping one file per entry in a directory, so entries survive restarts.
+///
+/// Files are only replaced by newer responses for the same URL and never evicted, so the
+/// directory grows with the number of distinct requests until it is cleared.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+/// First line of a cache file, followed by the body.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    key: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Seconds since the Unix epoch.
+    stored: u64,
+}
+
+impl DiskCache {
+    /// Store entries in `dir`, which is created if it does not exist.
+    pub fn new(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)?;
+        Ok(DiskCache { dir })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.cache", hash(key)))
+    }
+}
+
+/// FNV-1a, which unlike the std hasher is stable across Rust versions, for file names.
+pub(crate) fn hash(key: &str) -> u64 {
+    key.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl Cache for DiskCache {
+    fn get(&self, key: &str) -> Option<CacheEntry> {
+        let file = std::fs::read(self.path(key)).ok()?;
+        let newline = file.iter().position(|&byte| byte == b'\n')?;
+        let header: Header = serde_json::from_slice(&file[..newline]).ok()?;
+        // Two URLs with the same hash overwrite each other instead of being mixed up.
+        if header.key != key {
+            return None;
+        }
+        Some(CacheEntry {
+            body: Bytes::copy_from_slice(&file[newline + 1..]),
+            etag: header.etag,
+            last_modified: header.last_modified,
+            stored: UNIX_EPOCH + Duration::from_secs(header.stored),
+        })
+    }
+
+    fn put(&self, key: &str, entry: CacheEntry) {
+        let header = Header {
+            key: key.to_string(),
+            etag: entry.etag,
+            last_modified: entry.last_modified,
+            stored: entry
+                .stored
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+        };
+        let Ok(mut file) = serde_json::to_vec(&header) else {
+            return;
+        };
+        file.push(b'\n');
+        file.extend_from_slice(&entry.body);
+        // Write to a temporary file first so readers never see half an entry.
+        let path = self.path(key);
+        let temporary = path.with_extension(format!("{}.tmp", fastrand::u64(..)));
+        if std::fs::write(&temporary, file).is_err() || std::fs::rename(&temporary, &path).is_err()
+        {
+            let _ = std::fs::remove_file(&temporary);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = std::fs::remove_file(self.path(key));
+    }
+}
+
+/// How a response was answered by the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheStatus {
+    /// The entry was younger than its TTL and no request was sent.
+    Fresh,
+    /// The portal confirmed with 304 Not Modified that the entry is still current.
+    Revalidated,
+    /// The body was downloaded, because there was no entry or it had changed.
+    Miss,
+}
+
+/// Number of responses answered with each [`CacheStatus`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub fresh: u64,
+    pub revalidated: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Responses whose body didn't have to be downloaded.
+    pub fn hits(&self) -> u64 {
+        self.fresh + self.revalidated
+    }
+
+    fn record(&mut self, status: CacheStatus) {
+        match status {
+            CacheStatus::Fresh => self.fresh += 1,
+            CacheStatus::Revalidated => self.revalidated += 1,
+            CacheStatus::Miss => self.misses += 1,
+        }
+    }
+}
+
+/// HTTP cache for the JSON endpoints: records, aggregates, facets, metadata and the catalog.
+///
+/// Entries younger than their TTL are returned without a request. Older ones are revalidated
+/// with `If-None-Match` and `If-Modified-Since`, so an unchanged dataset costs a 304 response
+/// instead of the full body. Exports are streamed and never cached.
+///
+/// Clones share the storage, TTLs and statistics. The statistics count the responses of every
+/// request together, while each page of records tells its own status in [`Data::cache`].
+///
+/// [`Data::cache`]: crate::Data::cache
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    store: Arc<dyn Cache>,
+    ttl: Duration,
+    ttls: Arc<HashMap<String, Duration>>,
+    stats: Arc<Mutex<HashMap<String, CacheStats>>>,
+}
+
+impl HttpCache {
+    /// Cache in `store`, revalidating every entry before it is used.
+    pub fn new(store: impl Cache + 'static) -> Self {
+        HttpCache {
+            store: Arc::new(store),
+            ttl: Duration::ZERO,
+            ttls: Arc::default(),
+            stats: Arc::default(),
+        }
+    }
+
+    /// Use entries of datasets without their own TTL for `ttl` without revalidating them.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// TTL for one dataset by its id, e.g. `100039`.
+    pub fn dataset_ttl(mut self, dataset: &str, ttl: Duration) -> Self {
+        Arc::make_mut(&mut self.ttls).insert(dataset.to_string(), ttl);
+        self
+    }
+
+    /// TTL for one generated dataset module.
+    pub fn ttl_of<D: Dataset>(self, ttl: Duration) -> Self {
+        self.dataset_ttl(D::ID, ttl)
+    }
+
+    /// Statistics of all datasets together.
+    pub fn stats(&self) -> CacheStats {
+        self.counters()
+            .values()
+            .fold(CacheStats::default(), |total, stats| CacheStats {
+                fresh: total.fresh + stats.fresh,
+                revalidated: total.revalidated + stats.revalidated,
+                misses: total.misses + stats.misses,
+            })
+    }
+
+    /// Statistics of one dataset by its id, or `catalog` for catalog searches.
+    pub fn dataset_stats(&self, dataset: &str) -> CacheStats {
+        self.counters().get(dataset).copied().unwrap_or_default()
+    }
+
+    /// Forget all statistics.
+    pub fn reset_stats(&self) {
+        self.counters().clear();
+    }
+
+    pub fn store(&self) -> &dyn Cache {
+        &*self.store
+    }
+
+    fn counters(&self) -> std::sync::MutexGuard<'_, HashMap<String, CacheStats>> {
+        self.stats.lock().unwrap_or_else(|error| error.into_inner())
+    }
+}
+
+impl Client {
+    /// Body of a GET request, answered from `cache` where possible, and how it was answered.
+    pub(crate) async fn fetch_cached(
+        &self,
+        portal: Portal,
+        cache: &HttpCache,
+        dataset: &str,
+        url: reqwest::Url,
+    ) -> Result<(Bytes, CacheStatus), Error> {
+        let key = url.as_str().to_string();
+        let cached = cache.store.get(&key);
+        let ttl = cache.ttls.get(dataset).copied().unwrap_or(cache.ttl);
+        let mut headers = HeaderMap::new();
+        if let Some(entry) = &cached {
+            if entry.stored.elapsed().unwrap_or_default() < ttl {
+                cache
+                    .counters()
+                    .entry(dataset.to_string())
+                    .or_default()
+                    .record(CacheStatus::Fresh);
+                return Ok((entry.body.clone(), CacheStatus::Fresh));
+            }
+            let validators = [
+                (IF_NONE_MATCH, &entry.etag),
+                (IF_MODIFIED_SINCE, &entry.last_modified),
+            ];
+            for (name, value) in validators {
+                if let Some(value) = value
+                    .as_deref()
+                    .and_then(|value| HeaderValue::from_str(value).ok())
+                {
+                    headers.insert(name, value);
+                }
+            }
+        }
+
+        let stale = cached.is_some();
+        let response = self.send_with(portal, url, headers).await?;
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+        let (status, entry) = match cached {
+            Some(entry) if response.status() == StatusCode::NOT_MODIFIED => {
+                let entry = CacheEntry {
+                    etag: etag.or(entry.etag),
+                    last_modified: last_modified.or(entry.last_modified),
+                    stored: SystemTime::now(),
+                    ..entry
+                };
+                (CacheStatus::Revalidated, entry)
+            }
+            _ => {
+                let entry = CacheEntry {
+                    body: response.bytes().await?,
+                    etag,
+                    last_modified,
+                    stored: SystemTime::now(),
+                };
+                (CacheStatus::Miss, entry)
+            }
+        };
+        let body = entry.body.clone();
+        // Without validators or a TTL the entry could neither be fresh nor be revalidated.
+        if entry.etag.is_some() || entry.last_modified.is_some() || !ttl.is_zero() {
+            cache.store.put(&key, entry);
+        } else if stale {
+            cache.store.remove(&key);
+        }
+        cache
+            .counters()
+            .entry(dataset.to_string())
+            .or_default()
+            .record(status);
+        Ok((body, status))
+    }
+}
@@ -0,0 +1,148 @@
+//! Search the dataset catalogs of both portals.
+use crate::client::{Client, Portal};
+use crate::common::{escaped_facet, facet_value, quote_text, Data};
+use crate::error::Error;
+use crate::metadata::Metadata;
+use crate::registry::generated;
+
+/// Catalog query, rendered to the `where` and `refine` parameters.
+#[derive(Debug, Clone, Default)]
+pub struct Search {
+    filter: Vec<String>,
+    refine: Vec<String>,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Search::default()
+    }
+
+    /// Full text search over titles, descriptions and keywords.
+    pub fn text(mut self, text: &str) -> Self {
+        self.filter.push(format!("search({})", quote_text(text)));
+        self
+    }
+
+    /// Raw ODSQL condition on the catalog fields, e.g. `records_count > 1000`.
+    pub fn filter(mut self, condition: &str) -> Self {
+        self.filter.push(condition.to_string());
+        self
+    }
+
+    pub fn theme(mut self, theme: &str) -> Self {
+        self.refine.push(facet_value("theme", theme));
+        self
+    }
+
+    pub fn keyword(mut self, keyword: &str) -> Self {
+        self.refine.push(facet_value("keyword", keyword));
+        self
+    }
+
+    pub fn publisher(mut self, publisher: &str) -> Self {
+        self.refine.push(facet_value("publisher", publisher));
+        self
+    }
+
+    fn params(&self) -> Result<Vec<(&'static str, String)>, Error> {
+        if let Some(value) = escaped_facet(&self.refine) {
+            return Err(Error::InvalidValue(value));
+        }
+        let mut params = Vec::new();
+        if !self.filter.is_empty() {
+            let filter = self
+                .filter
+                .iter()
+                .map(|condition| format!("({condition})"))
+                .collect::<Vec<_>>()
+                .join(" and ");
+            params.push(("where", filter));
+        }
+        for refine in &self.refine {
+            params.push(("refine", refine.clone()));
+        }
+        Ok(params)
+    }
+}
+
+/// Catalog entry together with its place in this crate.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub portal: Portal,
+    /// Cargo feature of the dataset, e.g. `bs100003`, if this crate knows it.
+    pub feature: Option<&'static str>,
+    /// Module path, e.g. `stadt::basler_index_der_konsumentenpreise`, if its feature is enabled.
+    pub module: Option<&'static str>,
+    pub metadata: Metadata,
+}
+
+impl Hit {
+    fn new(portal: Portal, metadata: Metadata) -> Self {
+        let descriptor = generated()
+            .find(|descriptor| descriptor.portal == portal && descriptor.id == metadata.dataset_id);
+        Hit {
+            portal,
+            feature: descriptor.map(|descriptor| descriptor.feature),
+            module: descriptor
+                .filter(|descriptor| descriptor.enabled)
+                .map(|descriptor| descriptor.module),
+            metadata,
+        }
+    }
+}
+
+pub async fn search(
+    portal: Portal,
+    search: &Search,
+    limit: u8,
+    offset: u64,
+) -> Result<Data<Hit>, Error> {
+    search_with(&Client::new(), portal, search, limit, offset).await
+}
+
+pub async fn search_with(
+    client: &Client,
+    portal: Portal,
+    search: &Search,
+    limit: u8,
+    offset: u64,
+) -> Result<Data<Hit>, Error> {
+    let limit = if limit > 100 { 100 } else { limit };
+    let url = client.catalog_url(portal, "datasets");
+    let mut params = search.params()?;
+    params.push(("limit", limit.to_string()));
+    params.push(("offset", offset.to_string()));
+    let url = reqwest::Url::parse_with_params(&url, &params)?;
+    let (data, cache): (Data<Metadata>, _) = client.fetch_status(portal, "catalog", url).await?;
+    Ok(Data {
+        total_count: data.total_count,
+        results: data
+            .results
+            .into_iter()
+            .map(|metadata| Hit::new(portal, metadata))
+            .collect(),
+        cache,
+    })
+}
+
+/// Page through the catalogs of both portals and collect every hit.
+pub async fn search_all(search: &Search) -> Result<Vec<Hit>, Error> {
+    search_all_with(&Client::new(), search).await
+}
+
+pub async fn search_all_with(client: &Client, search: &Search) -> Result<Vec<Hit>, Error> {
+    let mut hits = Vec::new();
+    for portal in [Portal::Stadt, Portal::Land] {
+        let mut offset = 0;
+        loop {
+            let data = search_with(client, portal, search, 100, offset).await?;
+            offset += data.results.len() as u64;
+            let done = data.results.is_empty() || offset >= data.total_count;
+            hits.extend(data.results);
+            if done {
+                break;
+            }
+        }
+    }
+    Ok(hits)
+}
@@ -0,0 +1,378 @@
+#![allow(dead_code)]
+use crate::cache::{CacheStatus, HttpCache};
+use crate::common::{escaped_facet, escaped_name, quote_field, Data, Field, Order, Refine};
+use crate::error::Error;
+use crate::filter::Filter;
+use crate::retry::{RateLimiter, RetryPolicy};
+use reqwest::header::{HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+
+/// Open data portal a dataset is published on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Portal {
+    /// Kanton Basel-Stadt, <https://data.bs.ch>
+    Stadt,
+    /// Kanton Basel-Landschaft, <https://data.bl.ch>
+    Land,
+}
+
+impl Portal {
+    pub fn default_base_url(self) -> &'static str {
+        match self {
+            Portal::Stadt => "https://data.bs.ch",
+            Portal::Land => "https://data.bl.ch",
+        }
+    }
+}
+
+/// Connection to the Opendatasoft portals of both cantons.
+///
+/// Cloning is cheap and shares the underlying connection pool.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    stadt_url: String,
+    land_url: String,
+    retry: RetryPolicy,
+    limiter: Option<RateLimiter>,
+    cache: Option<HttpCache>,
+    #[cfg(feature = "testing")]
+    pub(crate) fixtures: Option<crate::testing::Fixtures>,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Client::new()
+    }
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self::with_http(reqwest::Client::new())
+    }
+
+    /// Use a preconfigured `reqwest::Client`, e.g. with a User-Agent, timeouts or a proxy.
+    pub fn with_http(http: reqwest::Client) -> Self {
+        Client {
+            http,
+            stadt_url: Portal::Stadt.default_base_url().to_string(),
+            land_url: Portal::Land.default_base_url().to_string(),
+            retry: RetryPolicy::none(),
+            limiter: None,
+            cache: None,
+            #[cfg(feature = "testing")]
+            fixtures: None,
+        }
+    }
+
+    /// Replace the base URL of a portal, e.g. with `http://localhost:8080` for a mock server.
+    pub fn base_url(mut self, portal: Portal, url: &str) -> Self {
+        let url = url.trim_end_matches('/').to_string();
+        match portal {
+            Portal::Stadt => self.stadt_url = url,
+            Portal::Land => self.land_url = url,
+        }
+        self
+    }
+
+    /// Retry throttled requests and transient errors, see [`RetryPolicy`].
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Limit the requests to both portals, including retries, see [`RateLimiter`].
+    pub fn rate_limit(mut self, limiter: RateLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Cache responses of the JSON endpoints, see [`HttpCache`].
+    pub fn cache(mut self, cache: HttpCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    pub fn portal_url(&self, portal: Portal) -> &str {
+        match portal {
+            Portal::Stadt => &self.stadt_url,
+            Portal::Land => &self.land_url,
+        }
+    }
+
+    pub(crate) fn catalog_url(&self, portal: Portal, path: &str) -> String {
+        format!(
+            "{}/api/explore/v2.1/catalog/{path}",
+            self.portal_url(portal)
+        )
+    }
+
+    /// URL of the catalog entry of `dataset`, or of one of its endpoints.
+    ///
+    /// The id goes into the path as is, so ids other than letters, digits, `_` and `-` are
+    /// rejected with [`Error::InvalidValue`] instead of requesting a different path.
+    pub(crate) fn dataset_url(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        endpoint: &str,
+    ) -> Result<String, Error> {
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+        if dataset.is_empty() || !dataset.chars().all(valid) {
+            return Err(Error::InvalidValue(format!("dataset id {dataset:?}")));
+        }
+        let path = match endpoint {
+            "" => format!("datasets/{dataset}"),
+            endpoint => format!("datasets/{dataset}/{endpoint}"),
+        };
+        Ok(self.catalog_url(portal, &path))
+    }
+
+    pub(crate) async fn records<R: DeserializeOwned, F: Field>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        limit: u8,
+        offset: u64,
+        order: Order<F>,
+        filter: Option<Filter<F>>,
+    ) -> Result<Data<R>, Error> {
+        let query = Query::new(order, filter);
+        self.records_page(portal, dataset, limit, offset, &query)
+            .await
+    }
+
+    /// Like `records`, but only the given fields are requested and filled in.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn records_select<R: DeserializeOwned, F: Field>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        fields: &[F],
+        limit: u8,
+        offset: u64,
+        order: Order<F>,
+        filter: Option<Filter<F>>,
+    ) -> Result<Data<R>, Error> {
+        let query = Query::new(order, filter).select(fields);
+        self.records_page(portal, dataset, limit, offset, &query)
+            .await
+    }
+
+    /// Like `records`, but narrowed down by facet refinements.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn records_refined<R: DeserializeOwned, F: Field>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        limit: u8,
+        offset: u64,
+        order: Order<F>,
+        filter: Option<Filter<F>>,
+        refine: Refine<F>,
+    ) -> Result<Data<R>, Error> {
+        let query = Query::new(order, filter).refine(refine);
+        self.records_page(portal, dataset, limit, offset, &query)
+            .await
+    }
+
+    pub(crate) async fn records_page<R: DeserializeOwned>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        limit: u8,
+        offset: u64,
+        query: &Query,
+    ) -> Result<Data<R>, Error> {
+        let limit = if limit > 100 { 100 } else { limit };
+        let url = self.dataset_url(portal, dataset, "records")?;
+        let mut params = query.params()?;
+        params.push(("limit", limit.to_string()));
+        params.push(("offset", offset.to_string()));
+        let url = reqwest::Url::parse_with_params(&url, &params)?;
+        let (mut data, cache): (Data<R>, _) = self.fetch_status(portal, dataset, url).await?;
+        data.cache = cache;
+        Ok(data)
+    }
+
+    /// Send a GET request and deserialize the JSON response.
+    pub(crate) async fn fetch<T: DeserializeOwned>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        url: reqwest::Url,
+    ) -> Result<T, Error> {
+        Ok(self.fetch_status(portal, dataset, url).await?.0)
+    }
+
+    /// Like `fetch`, together with how the cache answered, if there is one.
+    pub(crate) async fn fetch_status<T: DeserializeOwned>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        url: reqwest::Url,
+    ) -> Result<(T, Option<CacheStatus>), Error> {
+        let (body, status) = match &self.cache {
+            Some(cache) => {
+                let (body, status) = self.fetch_cached(portal, cache, dataset, url).await?;
+                (body, Some(status))
+            }
+            None => (self.send(portal, url).await?.bytes().await?, None),
+        };
+        Ok((deserialize(dataset, &body)?, status))
+    }
+
+    /// Send a GET request to `portal` and turn unsuccessful responses into errors.
+    ///
+    /// Waits for the rate limiter before every attempt and retries according to the policy.
+    pub(crate) async fn send(
+        &self,
+        portal: Portal,
+        url: reqwest::Url,
+    ) -> Result<reqwest::Response, Error> {
+        self.send_with(portal, url, HeaderMap::new()).await
+    }
+
+    /// Like `send`, with additional headers, e.g. for a conditional request.
+    ///
+    /// 304 Not Modified counts as success if `headers` make the request conditional, as it is
+    /// the expected answer then, and as an error otherwise. `portal` only picks the directory
+    /// of fixtures.
+    #[cfg_attr(not(feature = "testing"), allow(unused_variables))]
+    pub(crate) async fn send_with(
+        &self,
+        portal: Portal,
+        url: reqwest::Url,
+        headers: HeaderMap,
+    ) -> Result<reqwest::Response, Error> {
+        #[cfg(feature = "testing")]
+        if let Some(fixtures) = &self.fixtures {
+            return self.send_fixture(fixtures, portal, url).await;
+        }
+        self.send_live(url, headers).await
+    }
+
+    /// Like `send_with`, but always asking the portal.
+    pub(crate) async fn send_live(
+        &self,
+        url: reqwest::Url,
+        headers: HeaderMap,
+    ) -> Result<reqwest::Response, Error> {
+        let conditional =
+            headers.contains_key(IF_NONE_MATCH) || headers.contains_key(IF_MODIFIED_SINCE);
+        let mut retry = 0;
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+            let request = self.http.get(url.clone()).headers(headers.clone());
+            let delay = match request.send().await {
+                Ok(response)
+                    if response.status().is_success()
+                        || (conditional && response.status() == StatusCode::NOT_MODIFIED) =>
+                {
+                    return Ok(response)
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    match self.retry.after_status(retry, status, response.headers()) {
+                        Some(delay) => delay,
+                        None => {
+                            let body = response.text().await?;
+                            return Err(Error::from_response(status, &body));
+                        }
+                    }
+                }
+                Err(error) => match self.retry.after_error(retry, &error) {
+                    Some(delay) => delay,
+                    None => return Err(error.into()),
+                },
+            };
+            tokio::time::sleep(delay).await;
+            retry += 1;
+        }
+    }
+}
+
+/// ODSQL parameters shared by the records, exports and facets endpoints.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Query {
+    pub(crate) select: String,
+    pub(crate) filter: String,
+    pub(crate) group_by: String,
+    pub(crate) order: String,
+    pub(crate) refine: Vec<String>,
+    pub(crate) exclude: Vec<String>,
+    /// Value in the filter or order that can't be sent, see [`Error::InvalidValue`].
+    pub(crate) invalid: Option<String>,
+}
+
+impl Query {
+    pub(crate) fn new<F: Field>(order: Order<F>, filter: Option<Filter<F>>) -> Self {
+        Query {
+            invalid: filter
+                .as_ref()
+                .and_then(Filter::invalid)
+                .or_else(|| order.invalid()),
+            filter: filter.map(|filter| filter.to_string()).unwrap_or_default(),
+            order: order.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn select<F: Field>(mut self, fields: &[F]) -> Self {
+        self.select = fields
+            .iter()
+            .map(|field| quote_field(field.name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self
+    }
+
+    pub(crate) fn refine<F: Field>(mut self, refine: Refine<F>) -> Self {
+        self.refine = refine.refine;
+        self.exclude = refine.exclude;
+        self
+    }
+
+    /// Query string parameters, leaving out the ones that are not set.
+    pub(crate) fn params(&self) -> Result<Vec<(&'static str, String)>, Error> {
+        if let Some(value) = &self.invalid {
+            return Err(Error::InvalidValue(value.clone()));
+        }
+        let odsql = [&self.select, &self.filter, &self.group_by, &self.order];
+        let escaped = odsql
+            .into_iter()
+            .find_map(|odsql| escaped_name(odsql))
+            .or_else(|| escaped_facet(self.refine.iter().chain(&self.exclude)));
+        if let Some(value) = escaped {
+            return Err(Error::InvalidValue(value));
+        }
+        let refine = self.refine.iter().map(|value| ("refine", value));
+        let exclude = self.exclude.iter().map(|value| ("exclude", value));
+        let params = [
+            ("select", &self.select),
+            ("where", &self.filter),
+            ("group_by", &self.group_by),
+            ("order_by", &self.order),
+        ]
+        .into_iter()
+        .chain(refine)
+        .chain(exclude)
+        .filter(|(_, value)| !value.is_empty())
+        .map(|(name, value)| (name, value.clone()))
+        .collect();
+        Ok(params)
+    }
+}
+
+pub(crate) fn deserialize<T: DeserializeOwned>(dataset: &str, json: &[u8]) -> Result<T, Error> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(json);
+    serde_path_to_error::deserialize(deserializer)
+        .map_err(|error| Error::deserialize(dataset, error))
+}
@@ -1,10 +1,16 @@
 #![allow(dead_code, private_bounds)]
+use crate::cache::CacheStatus;
+use crate::expr::Expr;
+use crate::filter::Filter;
+use geojson::GeoJson;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
 use std::marker::PhantomData;
+use time::format_description::well_known::Rfc3339;
 use time::macros::format_description;
-use time::Date;
+use time::{Date, OffsetDateTime, UtcOffset};
 
-#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
 pub struct GeoPoint2d {
     pub lat: f64,
     pub lon: f64,
@@ -21,7 +27,9 @@ pub(crate) fn deserialize_date<'de, D>(deserializer: D) -> Result<Option<Date>,
 where
     D: Deserializer<'de>,
 {
-    let s = String::deserialize(deserializer)?;
+    let Some(s) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
     let format = format_description!("[year]-[month]-[day]");
     if let Ok(date) = time::Date::parse(&s, format) {
         Ok(Some(date))
@@ -46,126 +54,473 @@ pub(crate) fn serialize_date<S: Serializer>(
     }
 }
 
-fn escape(value: &str) -> String {
-    value.replace('\\', "\\\\").replace('"', "\\\"")
+/// ODSQL string literal.
+///
+/// Within double quotes only `\` and `"` are special, so escaping both keeps any text,
+/// newlines and other quotes included, inside a single literal.
+pub(crate) fn quote_text(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// ODSQL field name or alias, escaped within backticks like text within double quotes.
+pub(crate) fn quote_field(name: &str) -> String {
+    format!("`{}`", name.replace('\\', "\\\\").replace('`', "\\`"))
+}
+
+/// `refine` or `exclude` parameter keeping records where facet `name` has the value `value`.
+///
+/// The portal reads the facet name up to the first `:` and takes the rest as the value, so
+/// `value` is quoted like ODSQL text, though values that need escaping are rejected by
+/// [`escaped_facet`] for now, while `name` is sent as is and must not contain a `:`. Facet names are field names, which on the portals are made of lowercase
+/// letters, digits and underscores.
+pub(crate) fn facet_value(name: &str, value: &str) -> String {
+    format!("{name}:{}", quote_text(value))
+}
+
+/// First field name or alias in rendered ODSQL that needed escaping, for
+/// [`Error::InvalidValue`].
+///
+/// Whether the portals read `\` escapes within backticks the way they read them in text is
+/// unverified, see `tests/recorded`, so such names are rejected rather than risking a query
+/// on a different field.
+///
+/// [`Error::InvalidValue`]: crate::Error::InvalidValue
+pub(crate) fn escaped_name(odsql: &str) -> Option<String> {
+    let mut chars = odsql.char_indices();
+    while let Some((start, quote)) = chars.next() {
+        if !matches!(quote, '"' | '\'' | '`') {
+            continue;
+        }
+        let mut escaped = false;
+        while let Some((end, c)) = chars.next() {
+            if c == '\\' {
+                escaped = true;
+                chars.next();
+            } else if c == quote {
+                if quote == '`' && escaped {
+                    return Some(odsql[start..=end].to_string());
+                }
+                break;
+            }
+        }
+    }
+    None
+}
+
+/// First facet value that needed escaping, rejected like [`escaped_name`].
+pub(crate) fn escaped_facet<'a>(facets: impl IntoIterator<Item = &'a String>) -> Option<String> {
+    facets
+        .into_iter()
+        .find(|facet| facet.contains('\\'))
+        .cloned()
+}
+
+/// First number without an ODSQL literal, i.e. NaN or an infinity, for [`Error::InvalidValue`].
+///
+/// [`Error::InvalidValue`]: crate::Error::InvalidValue
+pub(crate) fn non_finite(numbers: impl IntoIterator<Item = f64>) -> Option<String> {
+    numbers
+        .into_iter()
+        .find(|number| !number.is_finite())
+        .map(|number| number.to_string())
+}
+
+/// Magnitude from which not every whole f64 is an exact integer anymore, 2^53.
+const EXACT_LIMIT: f64 = 9_007_199_254_740_992.0;
+
+/// Value to compare a field with, rendered as an ODSQL literal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Text(String),
+    Number(f64),
+    Integer(i64),
+    Date(Date),
+    DateTime(OffsetDateTime),
+}
+
+impl Literal {
+    pub(crate) fn render(&self) -> String {
+        match self {
+            Literal::Text(value) => quote_text(value),
+            // Larger whole numbers would read back as different or invalid integers.
+            Literal::Number(value) if value.fract() == 0.0 && value.abs() >= EXACT_LIMIT => {
+                format!("{value:e}")
+            }
+            Literal::Number(value) => format!("{value}"),
+            Literal::Integer(value) => format!("{value}"),
+            Literal::Date(date) => format!("date'{date}'"),
+            Literal::DateTime(datetime) => {
+                // Years RFC 3339 can't express are rejected by `invalid` before a request is sent.
+                let datetime = datetime
+                    .checked_to_offset(UtcOffset::UTC)
+                    .and_then(|datetime| datetime.format(&Rfc3339).ok())
+                    .unwrap_or_else(|| datetime.to_string());
+                format!("date'{datetime}'")
+            }
+        }
+    }
+
+    /// Value without an ODSQL literal: a number that isn't finite, or a date before the
+    /// year 0 or after 9999.
+    pub(crate) fn invalid(&self) -> Option<String> {
+        let year = |year: i32| !(0..=9999).contains(&year);
+        match self {
+            Literal::Number(number) => non_finite([*number]),
+            Literal::Date(date) if year(date.year()) => Some(date.to_string()),
+            Literal::DateTime(datetime) => match datetime.checked_to_offset(UtcOffset::UTC) {
+                Some(utc) if !year(utc.year()) => None,
+                _ => Some(datetime.to_string()),
+            },
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Literal {
+    fn from(value: &str) -> Self {
+        Literal::Text(value.to_string())
+    }
+}
+
+impl From<String> for Literal {
+    fn from(value: String) -> Self {
+        Literal::Text(value)
+    }
+}
+
+impl From<f64> for Literal {
+    fn from(value: f64) -> Self {
+        Literal::Number(value)
+    }
+}
+
+impl From<i64> for Literal {
+    fn from(value: i64) -> Self {
+        Literal::Integer(value)
+    }
+}
+
+impl From<i32> for Literal {
+    fn from(value: i32) -> Self {
+        Literal::Integer(value.into())
+    }
+}
+
+impl From<Date> for Literal {
+    fn from(value: Date) -> Self {
+        Literal::Date(value)
+    }
+}
+
+impl From<OffsetDateTime> for Literal {
+    fn from(value: OffsetDateTime) -> Self {
+        Literal::DateTime(value)
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Data<T> {
     pub total_count: u64,
     pub results: Vec<T>,
+    /// How the [`HttpCache`] answered the request for this page, `None` without a cache.
+    ///
+    /// [`HttpCache`]: crate::HttpCache
+    #[serde(skip)]
+    pub cache: Option<CacheStatus>,
+}
+
+/// Type of the values of a field, used to check parsed filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Text,
+    Integer,
+    Number,
+    Date,
+    DateTime,
+    GeoPoint,
+    GeoShape,
+    /// Not known before the portal sees the query, e.g. for dynamic fields.
+    Any,
 }
 
 pub(crate) trait Field {
-    fn name(self) -> &'static str;
+    fn name(&self) -> &str;
+    /// Field with the API name `name`, if the dataset has one.
+    fn from_name(name: &str) -> Option<Self>
+    where
+        Self: Sized;
+    fn field_type(&self) -> FieldType;
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+/// One key of an [`Order`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sort<T: Field> {
+    By(Expr<T>, Direction),
+    /// Alias of an aggregate or grouping key of the same query.
+    Alias(String, Direction),
+    /// Random order, the same for the same seed.
+    Random(u64),
+}
+
+impl<T: Field> fmt::Display for Sort<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (key, direction) = match self {
+            Sort::By(expression, direction) => (expression.render(), direction),
+            Sort::Alias(alias, direction) => (quote_field(alias), direction),
+            Sort::Random(seed) => return write!(f, "random({seed})"),
+        };
+        match direction {
+            Direction::Ascending => write!(f, "{key}"),
+            Direction::Descending => write!(f, "{key} desc"),
+        }
+    }
+}
+
+/// Sort keys, rendered to the `order_by` parameter. Later keys break ties of earlier ones.
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Order<T: Field> {
-    pub(crate) inner: String,
-    phantom: PhantomData<T>,
+    pub keys: Vec<Sort<T>>,
 }
 
 impl<T: Field> Order<T> {
     pub fn new() -> Self {
-        Order {
-            inner: String::new(),
-            phantom: PhantomData,
-        }
+        Order { keys: Vec::new() }
     }
 
-    pub fn ascending(mut self, field: T) -> Self {
-        if !self.inner.is_empty() {
-            self.inner.push_str(", ");
-        }
-        self.inner.push_str(&format!("`{}`", field.name()));
+    fn push(mut self, key: Sort<T>) -> Self {
+        self.keys.push(key);
         self
     }
 
-    pub fn descending(mut self, field: T) -> Self {
-        if !self.inner.is_empty() {
-            self.inner.push_str(", ");
+    pub fn ascending(self, field: T) -> Self {
+        self.ascending_by(Expr::Field(field))
+    }
+
+    pub fn descending(self, field: T) -> Self {
+        self.descending_by(Expr::Field(field))
+    }
+
+    /// Order by a computed value, e.g. `Expr::field(Field::Datum).year()`.
+    pub fn ascending_by(self, expression: Expr<T>) -> Self {
+        self.push(Sort::By(expression, Direction::Ascending))
+    }
+
+    pub fn descending_by(self, expression: Expr<T>) -> Self {
+        self.push(Sort::By(expression, Direction::Descending))
+    }
+
+    /// Order a grouped query by one of its aggregates, e.g. the alias given to `count`.
+    pub fn ascending_alias(self, alias: &str) -> Self {
+        self.push(Sort::Alias(alias.to_string(), Direction::Ascending))
+    }
+
+    pub fn descending_alias(self, alias: &str) -> Self {
+        self.push(Sort::Alias(alias.to_string(), Direction::Descending))
+    }
+
+    /// Shuffle the records, e.g. to sample them with a small `limit`.
+    ///
+    /// Paging through a random order only works with the same `seed` for every page.
+    pub fn random(self, seed: u64) -> Self {
+        self.push(Sort::Random(seed))
+    }
+
+    pub(crate) fn invalid(&self) -> Option<String> {
+        self.keys.iter().find_map(|key| match key {
+            Sort::By(expression, _) => expression.invalid(),
+            _ => None,
+        })
+    }
+}
+
+impl<T: Field> fmt::Display for Order<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, key) in self.keys.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{key}")?;
         }
-        self.inner.push_str(&format!("`{}` desc", field.name()));
-        self
+        Ok(())
     }
 }
 
+/// Facet refinements, rendered to the `refine` and `exclude` parameters.
 #[derive(Debug, Clone, Default)]
-pub struct Filter<T: Field> {
-    pub(crate) inner: String,
+pub struct Refine<T: Field> {
+    pub(crate) refine: Vec<String>,
+    pub(crate) exclude: Vec<String>,
     phantom: PhantomData<T>,
 }
 
-impl<T: Field> Filter<T> {
-    fn new(inner: String) -> Self {
-        Filter {
-            inner,
+impl<T: Field> Refine<T> {
+    pub fn new() -> Self {
+        Refine {
+            refine: Vec::new(),
+            exclude: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Only keep records where `field` has the facet value `value`.
+    ///
+    /// Values with a double quote or backslash fail with [`Error::InvalidValue`].
+    ///
+    /// [`Error::InvalidValue`]: crate::Error::InvalidValue
+    pub fn refine(mut self, field: T, value: &str) -> Self {
+        self.refine.push(facet_value(field.name(), value));
+        self
+    }
+
+    /// Drop records where `field` has the facet value `value`.
+    pub fn exclude(mut self, field: T, value: &str) -> Self {
+        self.exclude.push(facet_value(field.name(), value));
+        self
+    }
+}
+
+/// Types whose values have an order ODSQL can compare.
+pub trait Ordered: Into<Literal> {}
+
+impl Ordered for String {}
+impl Ordered for f64 {}
+impl Ordered for i64 {}
+impl Ordered for Date {}
+impl Ordered for OffsetDateTime {}
+
+/// Types of geographic fields.
+pub trait Geo {}
+
+impl Geo for GeoPoint2d {}
+impl Geo for GeoJson {}
+
+/// Field together with the Rust type of its values in `Record`.
+///
+/// Filters built from a typed field only accept values of that type, and text
+/// operations are only available on text fields.
+#[derive(Debug)]
+pub struct TypedField<T: Field, V> {
+    field: T,
+    phantom: PhantomData<fn() -> V>,
+}
+
+impl<T: Field + Clone, V> Clone for TypedField<T, V> {
+    fn clone(&self) -> Self {
+        TypedField {
+            field: self.field.clone(),
             phantom: PhantomData,
         }
     }
+}
+
+impl<T: Field + Copy, V> Copy for TypedField<T, V> {}
 
-    pub fn is_null(field: T) -> Self {
-        Self::new(format!("`{}` is null", field.name()))
+impl<T: Field, V> TypedField<T, V> {
+    pub const fn new(field: T) -> Self {
+        TypedField {
+            field,
+            phantom: PhantomData,
+        }
     }
 
-    pub fn is_not_null(field: T) -> Self {
-        Self::new(format!("`{}` is not null", field.name()))
+    pub fn field(self) -> T {
+        self.field
     }
 
-    pub fn equal_str(field: T, value: &str) -> Self {
-        Self::new(format!("`{}` = \"{}\"", field.name(), escape(value)))
+    /// Start an ODSQL expression on this field, e.g. `fields::DATUM.expr().year()`.
+    pub fn expr(self) -> Expr<T> {
+        Expr::Field(self.field)
     }
 
-    pub fn equal_num(field: T, value: f64) -> Self {
-        Self::new(format!("`{}` = {value}", field.name()))
+    pub fn is_null(self) -> Filter<T> {
+        Filter::is_null(self.field)
     }
 
-    pub fn not_equal_str(field: T, value: &str) -> Self {
-        Self::new(format!("`{}` != \"{}\"", field.name(), escape(value)))
+    pub fn is_not_null(self) -> Filter<T> {
+        Filter::is_not_null(self.field)
     }
+}
 
-    pub fn not_equal_num(field: T, value: f64) -> Self {
-        Self::new(format!("`{}` != {value}", field.name()))
+// The untyped constructors are deprecated for callers, not as the implementation.
+#[allow(deprecated)]
+impl<T: Field, V: Into<Literal>> TypedField<T, V> {
+    pub fn equal(self, value: impl Into<V>) -> Filter<T> {
+        Filter::equal(self.field, value.into())
     }
 
-    pub fn greater(field: T, value: f64) -> Self {
-        Self::new(format!("{} > {value}", field.name()))
+    pub fn not_equal(self, value: impl Into<V>) -> Filter<T> {
+        Filter::not_equal(self.field, value.into())
     }
 
-    pub fn greater_or_equal(field: T, value: f64) -> Self {
-        Self::new(format!("{} >= {value}", field.name()))
+    pub fn is_in<I: Into<V>>(self, values: impl IntoIterator<Item = I>) -> Filter<T> {
+        Filter::is_in(self.field, values.into_iter().map(Into::into))
     }
+}
 
-    pub fn less(field: T, value: f64) -> Self {
-        Self::new(format!("{} < {value}", field.name()))
+#[allow(deprecated)]
+impl<T: Field + Clone, V: Ordered> TypedField<T, V> {
+    pub fn greater(self, value: impl Into<V>) -> Filter<T> {
+        Filter::greater(self.field, value.into())
     }
 
-    pub fn less_or_equal(field: T, value: f64) -> Self {
-        Self::new(format!("{} <= {value}", field.name()))
+    pub fn greater_or_equal(self, value: impl Into<V>) -> Filter<T> {
+        Filter::greater_or_equal(self.field, value.into())
     }
 
-    pub fn starts_with(field: T, value: &str) -> Self {
-        Self::new(format!(
-            "startswith(`{}`, \"{}\")",
-            field.name(),
-            escape(value)
-        ))
+    pub fn less(self, value: impl Into<V>) -> Filter<T> {
+        Filter::less(self.field, value.into())
+    }
+
+    pub fn less_or_equal(self, value: impl Into<V>) -> Filter<T> {
+        Filter::less_or_equal(self.field, value.into())
+    }
+
+    pub fn between(self, low: impl Into<V>, high: impl Into<V>) -> Filter<T> {
+        Filter::between(self.field, low.into(), high.into())
+    }
+}
+
+#[allow(deprecated)]
+impl<T: Field> TypedField<T, String> {
+    pub fn starts_with(self, value: &str) -> Filter<T> {
+        Filter::starts_with(self.field, value)
+    }
+
+    pub fn like(self, pattern: &str) -> Filter<T> {
+        Filter::like(self.field, pattern)
+    }
+
+    pub fn search(self, value: &str) -> Filter<T> {
+        Filter::search(self.field, value)
+    }
+}
+
+#[allow(deprecated)]
+impl<T: Field, V: Geo> TypedField<T, V> {
+    pub fn within_distance(self, center: GeoPoint2d, meters: f64) -> Filter<T> {
+        Filter::within_distance(self.field, center, meters)
     }
 
-    pub fn search(field: T, value: &str) -> Self {
-        Self::new(format!("search(`{}`, \"{}\")", field.name(), escape(value)))
+    /// Meters from this field to `point`, e.g. for `Order::ascending_by`.
+    pub fn distance(self, point: GeoPoint2d) -> Expr<T> {
+        Expr::Distance(self.field, point)
     }
 
-    pub fn and(self, other: Self) -> Self {
-        Self::new(format!("({}) and ({})", self.inner, other.inner))
+    pub fn in_bbox(self, corner: GeoPoint2d, opposite: GeoPoint2d) -> Filter<T> {
+        Filter::in_bbox(self.field, corner, opposite)
     }
 
-    pub fn or(self, other: Self) -> Self {
-        Self::new(format!("({}) or ({})", self.inner, other.inner))
+    pub fn in_polygon(self, points: &[GeoPoint2d]) -> Filter<T> {
+        Filter::in_polygon(self.field, points)
     }
 
-    pub fn not(self) -> Self {
-        Self::new(format!("not ({})", self.inner))
+    pub fn intersects(self, geometry: &GeoJson) -> Filter<T> {
+        Filter::intersects(self.field, geometry)
     }
 }
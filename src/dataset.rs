@@ -0,0 +1,119 @@
+#![allow(private_bounds)]
+use crate::aggregate::{Aggregation, Group};
+use crate::client::{Client, Portal};
+use crate::common::{Data, Field, Order, Refine};
+use crate::error::Error;
+use crate::export::ExportFormat;
+use crate::facet::FacetValue;
+use crate::filter::Filter;
+use crate::metadata::Metadata;
+use bytes::Bytes;
+use futures_util::Stream;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// Compile-time description of a generated dataset module.
+///
+/// Every module in [`stadt`](crate::stadt) and [`land`](crate::land) has a unit struct
+/// `Dataset` implementing this trait, so code can be written once for all of them.
+pub trait Dataset {
+    type Record: DeserializeOwned + Serialize + Debug + Clone + Send + Sync + 'static;
+    type Field: Field + Debug + Clone + Copy + Send + Sync + 'static;
+    const PORTAL: Portal;
+    const ID: &'static str;
+    const TITLE: &'static str;
+    /// Cargo feature enabling the module, e.g. `bs100003`
+    const FEATURE: &'static str;
+}
+
+impl Client {
+    pub async fn get<D: Dataset>(
+        &self,
+        limit: u8,
+        offset: u64,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Result<Data<D::Record>, Error> {
+        self.records(D::PORTAL, D::ID, limit, offset, order, filter)
+            .await
+    }
+
+    /// All records matching `filter`, fetched page by page as the stream is polled.
+    ///
+    /// If more than [`MAX_OFFSET`](crate::MAX_OFFSET) records match, the only item is
+    /// [`Error::OffsetLimit`], also for consumers that just `take` a few records. Use `get`
+    /// for the first records of a larger result set, or `export` for all of them.
+    pub fn stream<D: Dataset>(
+        &self,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> impl Stream<Item = Result<D::Record, Error>> {
+        self.records_stream(D::PORTAL, D::ID, order, filter)
+    }
+
+    pub fn export<D: Dataset>(
+        &self,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> impl Stream<Item = Result<D::Record, Error>> {
+        self.export_records(D::PORTAL, D::ID, order, filter)
+    }
+
+    pub fn export_bytes<D: Dataset>(
+        &self,
+        format: ExportFormat,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> impl Stream<Item = Result<Bytes, Error>> {
+        self.export_raw(D::PORTAL, D::ID, format, order, filter)
+    }
+
+    pub async fn select<D: Dataset>(
+        &self,
+        fields: &[D::Field],
+        limit: u8,
+        offset: u64,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Result<Data<D::Record>, Error> {
+        self.records_select(D::PORTAL, D::ID, fields, limit, offset, order, filter)
+            .await
+    }
+
+    pub async fn get_refined<D: Dataset>(
+        &self,
+        limit: u8,
+        offset: u64,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+        refine: Refine<D::Field>,
+    ) -> Result<Data<D::Record>, Error> {
+        self.records_refined(D::PORTAL, D::ID, limit, offset, order, filter, refine)
+            .await
+    }
+
+    pub async fn aggregate<D: Dataset>(
+        &self,
+        aggregation: Aggregation<D::Field>,
+        order: Order<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Result<Vec<Group>, Error> {
+        self.records_aggregate(D::PORTAL, D::ID, aggregation, order, filter)
+            .await
+    }
+
+    pub async fn facets<D: Dataset>(
+        &self,
+        field: D::Field,
+        refine: Refine<D::Field>,
+        filter: Option<Filter<D::Field>>,
+    ) -> Result<Vec<FacetValue>, Error> {
+        self.dataset_facets(D::PORTAL, D::ID, field, refine, filter)
+            .await
+    }
+
+    pub async fn metadata<D: Dataset>(&self) -> Result<Metadata, Error> {
+        self.dataset_metadata(D::PORTAL, D::ID).await
+    }
+}
@@ -0,0 +1,179 @@
+//! Access to datasets that are not compiled into this crate.
+//!
+//! Field names are plain strings, so nothing is checked before the portal sees the query.
+use crate::aggregate::{Aggregation, Group};
+use crate::client::{Client, Portal};
+use crate::common::{Data, FieldType, Order, Refine};
+use crate::error::Error;
+use crate::export::ExportFormat;
+use crate::facet::FacetValue;
+use crate::filter::Filter;
+use crate::metadata::Metadata;
+use bytes::Bytes;
+use futures_util::Stream;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Field of a dynamic dataset, identified by its name on the portal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Field(String);
+
+impl Field {
+    pub fn new(name: &str) -> Self {
+        Field(name.to_string())
+    }
+}
+
+impl crate::common::Field for Field {
+    fn name(&self) -> &str {
+        &self.0
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(Field::new(name))
+    }
+
+    fn field_type(&self) -> FieldType {
+        FieldType::Any
+    }
+}
+
+/// Handle to any dataset of either portal.
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    client: Client,
+    portal: Portal,
+    id: String,
+}
+
+impl Dataset {
+    pub fn new(portal: Portal, id: &str) -> Self {
+        Dataset {
+            client: Client::new(),
+            portal,
+            id: id.to_string(),
+        }
+    }
+
+    /// Send all requests through `client` instead of a default one.
+    pub fn client(mut self, client: &Client) -> Self {
+        self.client = client.clone();
+        self
+    }
+
+    pub fn portal(&self) -> Portal {
+        self.portal
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub async fn get(
+        &self,
+        limit: u8,
+        offset: u64,
+        order: Order<Field>,
+        filter: Option<Filter<Field>>,
+    ) -> Result<Data<Value>, Error> {
+        self.get_as(limit, offset, order, filter).await
+    }
+
+    /// Like `get`, but records are deserialized into `R`.
+    pub async fn get_as<R: DeserializeOwned>(
+        &self,
+        limit: u8,
+        offset: u64,
+        order: Order<Field>,
+        filter: Option<Filter<Field>>,
+    ) -> Result<Data<R>, Error> {
+        self.client
+            .records(self.portal, &self.id, limit, offset, order, filter)
+            .await
+    }
+
+    pub async fn get_refined<R: DeserializeOwned>(
+        &self,
+        limit: u8,
+        offset: u64,
+        order: Order<Field>,
+        filter: Option<Filter<Field>>,
+        refine: Refine<Field>,
+    ) -> Result<Data<R>, Error> {
+        self.client
+            .records_refined(self.portal, &self.id, limit, offset, order, filter, refine)
+            .await
+    }
+
+    pub async fn select<R: DeserializeOwned>(
+        &self,
+        fields: &[Field],
+        limit: u8,
+        offset: u64,
+        order: Order<Field>,
+        filter: Option<Filter<Field>>,
+    ) -> Result<Data<R>, Error> {
+        self.client
+            .records_select(self.portal, &self.id, fields, limit, offset, order, filter)
+            .await
+    }
+
+    /// All records matching `filter`, fetched page by page as the stream is polled.
+    ///
+    /// If more than [`MAX_OFFSET`](crate::MAX_OFFSET) records match, the only item is
+    /// [`Error::OffsetLimit`], also for consumers that just `take` a few records. Use `get`
+    /// for the first records of a larger result set, or `export` for all of them.
+    pub fn stream<R: DeserializeOwned + Send + 'static>(
+        &self,
+        order: Order<Field>,
+        filter: Option<Filter<Field>>,
+    ) -> impl Stream<Item = Result<R, Error>> {
+        self.client
+            .records_stream(self.portal, &self.id, order, filter)
+    }
+
+    pub fn export<R: DeserializeOwned + Send + 'static>(
+        &self,
+        order: Order<Field>,
+        filter: Option<Filter<Field>>,
+    ) -> impl Stream<Item = Result<R, Error>> {
+        self.client
+            .export_records(self.portal, &self.id, order, filter)
+    }
+
+    pub fn export_bytes(
+        &self,
+        format: ExportFormat,
+        order: Order<Field>,
+        filter: Option<Filter<Field>>,
+    ) -> impl Stream<Item = Result<Bytes, Error>> {
+        self.client
+            .export_raw(self.portal, &self.id, format, order, filter)
+    }
+
+    pub async fn aggregate(
+        &self,
+        aggregation: Aggregation<Field>,
+        order: Order<Field>,
+        filter: Option<Filter<Field>>,
+    ) -> Result<Vec<Group>, Error> {
+        self.client
+            .records_aggregate(self.portal, &self.id, aggregation, order, filter)
+            .await
+    }
+
+    pub async fn facets(
+        &self,
+        field: Field,
+        refine: Refine<Field>,
+        filter: Option<Filter<Field>>,
+    ) -> Result<Vec<FacetValue>, Error> {
+        self.client
+            .dataset_facets(self.portal, &self.id, field, refine, filter)
+            .await
+    }
+
+    pub async fn metadata(&self) -> Result<Metadata, Error> {
+        self.client.dataset_metadata(self.portal, &self.id).await
+    }
+}
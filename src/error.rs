@@ -0,0 +1,194 @@
+use crate::parse::ParseError;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Everything that can go wrong when querying a portal.
+#[derive(Debug)]
+pub enum Error {
+    /// The request URL could not be built, e.g. because of an invalid base URL.
+    Url(url::ParseError),
+    /// The request could not be sent or the response body could not be read.
+    Transport(reqwest::Error),
+    /// The portal answered with a non-success status code.
+    ///
+    /// `error_code` and `message` are taken from the Opendatasoft error body if there is one.
+    Status {
+        status: StatusCode,
+        error_code: Option<String>,
+        message: Option<String>,
+    },
+    /// The portal rejected the ODSQL of a `where`, `order_by` or `select` parameter.
+    Query { error_code: String, message: String },
+    /// A paginated query matches more records than the offset ceiling of the records endpoint.
+    ///
+    /// Use the exports endpoint to read all `total_count` records. Streams fail with it before
+    /// yielding any record, so that a result set is never cut off silently.
+    OffsetLimit { total_count: u64 },
+    /// An aggregation has more groups than the records endpoint returns in one response.
+    ///
+    /// Narrow the filter or group by fewer fields to stay within [`MAX_GROUPS`](crate::MAX_GROUPS).
+    TooManyGroups { total_count: u64 },
+    /// A filter, order or percentile contains a value ODSQL has no literal for, i.e. NaN, an
+    /// infinity or a date outside the years 0 to 9999, a negative distance, or a percentile
+    /// outside 0 to 100. Also field names with a backtick or backslash and refined values with
+    /// a double quote or backslash, whose escaping hasn't been checked against the portals
+    /// yet, and dataset ids other than letters, digits, `_` and `-`.
+    InvalidValue(String),
+    /// A filter given as ODSQL text could not be parsed.
+    Parse(ParseError),
+    /// A fixture of the `testing` feature could not be read or written.
+    Fixture {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The response did not match the expected schema.
+    Deserialize {
+        dataset: String,
+        path: String,
+        source: serde_json::Error,
+    },
+}
+
+impl Error {
+    /// HTTP status code of the response, if the error was caused by one.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Status { status, .. } => Some(*status),
+            Error::Query { .. } => Some(StatusCode::BAD_REQUEST),
+            Error::Transport(error) => error.status(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_response(status: StatusCode, body: &str) -> Self {
+        #[derive(Deserialize)]
+        struct Body {
+            error_code: Option<String>,
+            message: Option<String>,
+        }
+
+        let body = serde_json::from_str::<Body>(body).ok();
+        let (error_code, message) = body
+            .map(|body| (body.error_code, body.message))
+            .unwrap_or_default();
+        match (status, error_code, message) {
+            (StatusCode::BAD_REQUEST, Some(error_code), Some(message))
+                if error_code.contains("ODSQL") =>
+            {
+                Error::Query {
+                    error_code,
+                    message,
+                }
+            }
+            (status, error_code, message) => Error::Status {
+                status,
+                error_code,
+                message,
+            },
+        }
+    }
+
+    pub(crate) fn deserialize(
+        dataset: &str,
+        error: serde_path_to_error::Error<serde_json::Error>,
+    ) -> Self {
+        Error::Deserialize {
+            dataset: dataset.to_string(),
+            path: error.path().to_string(),
+            source: error.into_inner(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Url(error) => write!(f, "invalid url: {error}"),
+            Error::Transport(error) => write!(f, "request failed: {error}"),
+            Error::Status {
+                status,
+                error_code,
+                message,
+            } => {
+                write!(f, "portal responded with {status}")?;
+                if let Some(error_code) = error_code {
+                    write!(f, " ({error_code})")?;
+                }
+                if let Some(message) = message {
+                    write!(f, ": {message}")?;
+                }
+                Ok(())
+            }
+            Error::Query {
+                error_code,
+                message,
+            } => write!(f, "invalid query ({error_code}): {message}"),
+            Error::OffsetLimit { total_count } => write!(
+                f,
+                "query matches {total_count} records, but only the first {} can be paged through",
+                crate::stream::MAX_OFFSET
+            ),
+            Error::TooManyGroups { total_count } => write!(
+                f,
+                "aggregation has {total_count} groups, but only {} are returned",
+                crate::aggregate::MAX_GROUPS
+            ),
+            Error::InvalidValue(value) => write!(f, "{value} can't be used in a query"),
+            Error::Parse(error) => write!(f, "invalid filter: {error}"),
+            Error::Fixture { path, source } => {
+                write!(f, "fixture {}: {source}", path.display())
+            }
+            Error::Deserialize {
+                dataset,
+                path,
+                source,
+            } => write!(
+                f,
+                "unexpected data in dataset {dataset} at `{path}`: {source}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Url(error) => Some(error),
+            Error::Transport(error) => Some(error),
+            Error::Parse(error) => Some(error),
+            Error::Fixture { source, .. } => Some(source),
+            Error::Deserialize { source, .. } => Some(source),
+            Error::Status { .. }
+            | Error::Query { .. }
+            | Error::OffsetLimit { .. }
+            | Error::TooManyGroups { .. }
+            | Error::InvalidValue(_) => None,
+        }
+    }
+}
+
+impl From<url::ParseError> for Error {
+    fn from(error: url::ParseError) -> Self {
+        Error::Url(error)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        Error::Parse(error)
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        Error::Transport(error)
+    }
+}
+
+// Errors have to cross `tokio::spawn` boundaries.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Error>();
+};
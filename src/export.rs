@@ -0,0 +1,121 @@
+#![allow(dead_code)]
+use crate::client::{deserialize, Client, Portal, Query};
+use crate::common::{Field, Order};
+use crate::error::Error;
+use crate::filter::Filter;
+use bytes::Bytes;
+use futures_util::stream::{self, BoxStream, Stream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+
+/// File format of the exports endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportFormat {
+    /// A single JSON array of records.
+    Json,
+    /// One JSON record per line.
+    Jsonl,
+    /// Semicolon separated values with a header row.
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Jsonl => "jsonl",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+struct Lines<R> {
+    dataset: String,
+    body: BoxStream<'static, Result<Bytes, Error>>,
+    buffer: Vec<u8>,
+    done: bool,
+    phantom: PhantomData<R>,
+}
+
+impl<R: DeserializeOwned> Lines<R> {
+    async fn next(mut self) -> Option<(Result<R, Error>, Self)> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                if line.trim_ascii().is_empty() {
+                    continue;
+                }
+                let record = deserialize(&self.dataset, &line);
+                return Some((record, self));
+            }
+            if self.done {
+                if self.buffer.trim_ascii().is_empty() {
+                    return None;
+                }
+                let line = std::mem::take(&mut self.buffer);
+                let record = deserialize(&self.dataset, &line);
+                return Some((record, self));
+            }
+            match self.body.next().await {
+                Some(Ok(chunk)) => self.buffer.extend_from_slice(&chunk),
+                Some(Err(error)) => {
+                    self.buffer.clear();
+                    self.done = true;
+                    return Some((Err(error), self));
+                }
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Download all records matching `filter` from the exports endpoint as raw bytes.
+    ///
+    /// Unlike the records endpoint, exports are not limited to the first 10 000 records.
+    pub(crate) fn export_raw<F: Field>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        format: ExportFormat,
+        order: Order<F>,
+        filter: Option<Filter<F>>,
+    ) -> impl Stream<Item = Result<Bytes, Error>> + Send + 'static {
+        let client = self.clone();
+        let url = self.dataset_url(portal, dataset, &format!("exports/{}", format.name()));
+        let params = Query::new(order, filter).params();
+        stream::once(async move {
+            let url = reqwest::Url::parse_with_params(&url?, &params?)?;
+            let response = client.send(portal, url).await?;
+            Ok::<_, Error>(response.bytes_stream().map_err(Error::from))
+        })
+        .try_flatten()
+    }
+
+    /// Download and deserialize all records matching `filter` from the exports endpoint.
+    ///
+    /// The JSON Lines export is decoded record by record as it arrives, so the whole
+    /// dataset is never held in memory.
+    pub(crate) fn export_records<R, F>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        order: Order<F>,
+        filter: Option<Filter<F>>,
+    ) -> impl Stream<Item = Result<R, Error>> + Send + 'static
+    where
+        R: DeserializeOwned + Send + 'static,
+        F: Field,
+    {
+        let lines = Lines {
+            dataset: dataset.to_string(),
+            body: self
+                .export_raw(portal, dataset, ExportFormat::Jsonl, order, filter)
+                .boxed(),
+            buffer: Vec::new(),
+            done: false,
+            phantom: PhantomData,
+        };
+        stream::unfold(lines, Lines::next)
+    }
+}
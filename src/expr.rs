@@ -0,0 +1,523 @@
+#![allow(private_bounds)]
+use crate::common::{non_finite, quote_field, Field, GeoPoint2d, Literal};
+use crate::filter::{distance, geom, points, wkt_point, Comparison, Filter};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use time::format_description::well_known::{Iso8601, Rfc3339};
+use time::macros::format_description;
+use time::{Date, Duration, Month, OffsetDateTime};
+
+/// ODSQL scalar function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Function {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    DateFormat,
+    Lower,
+    Upper,
+    Length,
+}
+
+impl Function {
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Year => "year",
+            Function::Month => "month",
+            Function::Day => "day",
+            Function::Hour => "hour",
+            Function::Minute => "minute",
+            Function::Second => "second",
+            Function::DateFormat => "date_format",
+            Function::Lower => "lower",
+            Function::Upper => "upper",
+            Function::Length => "length",
+        }
+    }
+
+    fn apply(self, arguments: &[Scalar]) -> Scalar {
+        let Some(argument) = arguments.first() else {
+            return Scalar::Null;
+        };
+        let part = |part: fn(OffsetDateTime) -> i64| {
+            argument.datetime().map_or(Scalar::Null, |datetime| {
+                Scalar::Number(part(datetime) as f64)
+            })
+        };
+        let text = |map: fn(String) -> Scalar| argument.text().map_or(Scalar::Null, map);
+        match self {
+            Function::Year => part(|datetime| datetime.year().into()),
+            Function::Month => part(|datetime| (datetime.month() as u8).into()),
+            Function::Day => part(|datetime| datetime.day().into()),
+            Function::Hour => part(|datetime| datetime.hour().into()),
+            Function::Minute => part(|datetime| datetime.minute().into()),
+            Function::Second => part(|datetime| datetime.second().into()),
+            Function::DateFormat => {
+                let pattern = arguments.get(1).and_then(Scalar::text);
+                match (argument.datetime(), pattern) {
+                    (Some(datetime), Some(pattern)) => {
+                        Scalar::Text(date_format(datetime, &pattern))
+                    }
+                    _ => Scalar::Null,
+                }
+            }
+            Function::Lower => text(|text| Scalar::Text(text.to_lowercase())),
+            Function::Upper => text(|text| Scalar::Text(text.to_uppercase())),
+            Function::Length => text(|text| Scalar::Number(text.chars().count() as f64)),
+        }
+    }
+}
+
+/// Format `datetime` with the ICU pattern letters `y`, `M`, `d`, `H`, `m` and `s`.
+fn date_format(datetime: OffsetDateTime, pattern: &str) -> String {
+    let mut formatted = String::new();
+    let mut chars = pattern.chars().peekable();
+    while let Some(letter) = chars.next() {
+        if letter == '\'' {
+            for quoted in chars.by_ref() {
+                if quoted == '\'' {
+                    break;
+                }
+                formatted.push(quoted);
+            }
+            continue;
+        }
+        let mut width = 1;
+        while chars.peek() == Some(&letter) {
+            chars.next();
+            width += 1;
+        }
+        let value: i64 = match letter {
+            'y' if width == 2 => (datetime.year() % 100).into(),
+            'y' => datetime.year().into(),
+            'M' => (datetime.month() as u8).into(),
+            'd' => datetime.day().into(),
+            'H' => datetime.hour().into(),
+            'm' => datetime.minute().into(),
+            's' => datetime.second().into(),
+            _ => {
+                formatted.extend(std::iter::repeat_n(letter, width));
+                continue;
+            }
+        };
+        formatted.push_str(&format!("{value:0width$}"));
+    }
+    formatted
+}
+
+/// Offset of `now()`, e.g. `Shift::days(-7)` for one week ago.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Shift {
+    pub years: i64,
+    pub months: i64,
+    pub weeks: i64,
+    pub days: i64,
+    pub hours: i64,
+    pub minutes: i64,
+    pub seconds: i64,
+}
+
+impl Shift {
+    pub fn years(years: i64) -> Self {
+        Shift {
+            years,
+            ..Default::default()
+        }
+    }
+
+    pub fn months(months: i64) -> Self {
+        Shift {
+            months,
+            ..Default::default()
+        }
+    }
+
+    pub fn weeks(weeks: i64) -> Self {
+        Shift {
+            weeks,
+            ..Default::default()
+        }
+    }
+
+    pub fn days(days: i64) -> Self {
+        Shift {
+            days,
+            ..Default::default()
+        }
+    }
+
+    pub fn hours(hours: i64) -> Self {
+        Shift {
+            hours,
+            ..Default::default()
+        }
+    }
+
+    pub fn minutes(minutes: i64) -> Self {
+        Shift {
+            minutes,
+            ..Default::default()
+        }
+    }
+
+    pub fn seconds(seconds: i64) -> Self {
+        Shift {
+            seconds,
+            ..Default::default()
+        }
+    }
+
+    fn render(&self) -> String {
+        [
+            ("years", self.years),
+            ("months", self.months),
+            ("weeks", self.weeks),
+            ("days", self.days),
+            ("hours", self.hours),
+            ("minutes", self.minutes),
+            ("seconds", self.seconds),
+        ]
+        .into_iter()
+        .filter(|(_, value)| *value != 0)
+        .map(|(unit, value)| format!("{unit}={value}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+
+    fn apply(&self, datetime: OffsetDateTime) -> OffsetDateTime {
+        let months = self.years.saturating_mul(12).saturating_add(self.months);
+        let total = i64::from(datetime.year()) * 12 + i64::from(datetime.month() as u8) - 1;
+        let total = total.saturating_add(months);
+        let shifted = i32::try_from(total.div_euclid(12))
+            .ok()
+            .zip(Month::try_from(total.rem_euclid(12) as u8 + 1).ok())
+            .and_then(|(year, month)| {
+                let day = datetime.day().min(month.length(year));
+                Date::from_calendar_date(year, month, day).ok()
+            })
+            .map_or(datetime, |date| datetime.replace_date(date));
+        let seconds = self
+            .weeks
+            .saturating_mul(604_800)
+            .saturating_add(self.days.saturating_mul(86_400))
+            .saturating_add(self.hours.saturating_mul(3_600))
+            .saturating_add(self.minutes.saturating_mul(60))
+            .saturating_add(self.seconds);
+        shifted
+            .checked_add(Duration::seconds(seconds))
+            .unwrap_or(shifted)
+    }
+}
+
+/// Value of an expression evaluated against a record.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Scalar {
+    Null,
+    Text(String),
+    Number(f64),
+    Date(Date),
+    DateTime(OffsetDateTime),
+    Other(Value),
+}
+
+impl Scalar {
+    pub(crate) fn from_value(value: &Value) -> Self {
+        match value {
+            Value::Null => Scalar::Null,
+            Value::String(text) => Scalar::Text(text.clone()),
+            Value::Number(number) => number.as_f64().map_or(Scalar::Null, Scalar::Number),
+            value => Scalar::Other(value.clone()),
+        }
+    }
+
+    pub(crate) fn is_null(&self) -> bool {
+        *self == Scalar::Null
+    }
+
+    pub(crate) fn text(&self) -> Option<String> {
+        match self {
+            Scalar::Null => None,
+            Scalar::Text(text) => Some(text.clone()),
+            Scalar::Number(number) => Some(number.to_string()),
+            Scalar::Date(date) => Some(date.to_string()),
+            Scalar::DateTime(datetime) => datetime.format(&Rfc3339).ok(),
+            Scalar::Other(value) => Some(value.to_string()),
+        }
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Scalar::Number(number) => Some(*number),
+            Scalar::Text(text) => text.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Dates count as midnight UTC, text is parsed as RFC 3339, ISO 8601 or a plain date.
+    pub(crate) fn datetime(&self) -> Option<OffsetDateTime> {
+        match self {
+            Scalar::Date(date) => Some(date.midnight().assume_utc()),
+            Scalar::DateTime(datetime) => Some(*datetime),
+            Scalar::Text(text) => OffsetDateTime::parse(text, &Rfc3339)
+                .or_else(|_| OffsetDateTime::parse(text, &Iso8601::DEFAULT))
+                .ok()
+                .or_else(|| {
+                    Date::parse(text, format_description!("[year]-[month]-[day]"))
+                        .ok()
+                        .map(|date| date.midnight().assume_utc())
+                }),
+            _ => None,
+        }
+    }
+
+    /// Order of two values, or `None` if either is null or they can't be compared.
+    pub(crate) fn compare(&self, other: &Scalar) -> Option<Ordering> {
+        use Scalar::*;
+        match (self, other) {
+            (Null, _) | (_, Null) => None,
+            (Date(_) | DateTime(_), _) | (_, Date(_) | DateTime(_)) => {
+                Some(self.datetime()?.cmp(&other.datetime()?))
+            }
+            (Number(_), _) | (_, Number(_)) => self.number()?.partial_cmp(&other.number()?),
+            (Text(left), Text(right)) => Some(left.cmp(right)),
+            (Other(left), Other(right)) => (left == right).then_some(Ordering::Equal),
+            _ => None,
+        }
+    }
+}
+
+impl From<&Literal> for Scalar {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Text(text) => Scalar::Text(text.clone()),
+            Literal::Number(number) => Scalar::Number(*number),
+            Literal::Integer(integer) => Scalar::Number(*integer as f64),
+            Literal::Date(date) => Scalar::Date(*date),
+            Literal::DateTime(datetime) => Scalar::DateTime(*datetime),
+        }
+    }
+}
+
+/// Value of the field `name` in a serialized record.
+///
+/// Fields whose names start with a digit are prefixed with `x` in `Record`. Other names have
+/// no prefix, so a missing `name` is null rather than whatever `x{name}` holds.
+pub(crate) fn lookup<'a>(record: &'a Map<String, Value>, name: &str) -> &'a Value {
+    static NULL: Value = Value::Null;
+    let generated = name.starts_with(|c: char| c.is_ascii_digit());
+    record
+        .get(name)
+        .or_else(|| generated.then(|| record.get(&format!("x{name}"))).flatten())
+        .unwrap_or(&NULL)
+}
+
+/// Typed ODSQL expression over the fields of a dataset.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr<T: Field> {
+    Field(T),
+    Literal(Literal),
+    Now(Shift),
+    Call(Function, Vec<Expr<T>>),
+    /// Distance in meters between a geographic field and a point.
+    Distance(T, GeoPoint2d),
+}
+
+impl<T: Field> Expr<T> {
+    pub fn field(field: T) -> Self {
+        Expr::Field(field)
+    }
+
+    pub fn literal(value: impl Into<Literal>) -> Self {
+        Expr::Literal(value.into())
+    }
+
+    pub fn now() -> Self {
+        Expr::Now(Shift::default())
+    }
+
+    /// Meters from `field` to `point`, e.g. to order by nearest first.
+    pub fn distance(field: T, point: GeoPoint2d) -> Self {
+        Expr::Distance(field, point)
+    }
+
+    /// `now()` moved by `shift`, e.g. `Expr::now_plus(Shift::hours(-24))`.
+    pub fn now_plus(shift: Shift) -> Self {
+        Expr::Now(shift)
+    }
+
+    fn call(self, function: Function) -> Self {
+        Expr::Call(function, vec![self])
+    }
+
+    pub fn year(self) -> Self {
+        self.call(Function::Year)
+    }
+
+    pub fn month(self) -> Self {
+        self.call(Function::Month)
+    }
+
+    pub fn day(self) -> Self {
+        self.call(Function::Day)
+    }
+
+    pub fn hour(self) -> Self {
+        self.call(Function::Hour)
+    }
+
+    pub fn minute(self) -> Self {
+        self.call(Function::Minute)
+    }
+
+    pub fn second(self) -> Self {
+        self.call(Function::Second)
+    }
+
+    /// Format a date with an ICU pattern such as `yyyy-MM`.
+    pub fn date_format(self, pattern: &str) -> Self {
+        Expr::Call(Function::DateFormat, vec![self, Expr::literal(pattern)])
+    }
+
+    pub fn lower(self) -> Self {
+        self.call(Function::Lower)
+    }
+
+    pub fn upper(self) -> Self {
+        self.call(Function::Upper)
+    }
+
+    pub fn length(self) -> Self {
+        self.call(Function::Length)
+    }
+
+    pub(crate) fn render(&self) -> String {
+        match self {
+            Expr::Field(field) => quote_field(field.name()),
+            Expr::Literal(literal) => literal.render(),
+            Expr::Now(shift) => format!("now({})", shift.render()),
+            Expr::Call(function, arguments) => {
+                let arguments: Vec<String> = arguments.iter().map(Expr::render).collect();
+                format!("{}({})", function.name(), arguments.join(", "))
+            }
+            Expr::Distance(field, point) => format!(
+                "distance({}, {})",
+                quote_field(field.name()),
+                geom(&wkt_point(*point))
+            ),
+        }
+    }
+
+    pub(crate) fn invalid(&self) -> Option<String> {
+        match self {
+            Expr::Literal(literal) => literal.invalid(),
+            Expr::Call(_, arguments) => arguments.iter().find_map(Expr::invalid),
+            Expr::Distance(_, point) => non_finite([point.lat, point.lon]),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn evaluate(&self, record: &Map<String, Value>, now: OffsetDateTime) -> Scalar {
+        match self {
+            Expr::Field(field) => Scalar::from_value(lookup(record, field.name())),
+            Expr::Literal(literal) => literal.into(),
+            Expr::Now(shift) => Scalar::DateTime(shift.apply(now)),
+            Expr::Call(function, arguments) => {
+                let arguments: Vec<Scalar> = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(record, now))
+                    .collect();
+                function.apply(&arguments)
+            }
+            Expr::Distance(field, point) => points(lookup(record, field.name()))
+                .first()
+                .map_or(Scalar::Null, |&from| {
+                    Scalar::Number(distance(from, (point.lon, point.lat)))
+                }),
+        }
+    }
+
+    fn compare(self, comparison: Comparison, other: impl Into<Expr<T>>) -> Filter<T> {
+        Filter::Compare(self, comparison, other.into())
+    }
+
+    pub fn equal(self, other: impl Into<Expr<T>>) -> Filter<T> {
+        self.compare(Comparison::Equal, other)
+    }
+
+    pub fn not_equal(self, other: impl Into<Expr<T>>) -> Filter<T> {
+        self.compare(Comparison::NotEqual, other)
+    }
+
+    pub fn greater(self, other: impl Into<Expr<T>>) -> Filter<T> {
+        self.compare(Comparison::Greater, other)
+    }
+
+    pub fn greater_or_equal(self, other: impl Into<Expr<T>>) -> Filter<T> {
+        self.compare(Comparison::GreaterOrEqual, other)
+    }
+
+    pub fn less(self, other: impl Into<Expr<T>>) -> Filter<T> {
+        self.compare(Comparison::Less, other)
+    }
+
+    pub fn less_or_equal(self, other: impl Into<Expr<T>>) -> Filter<T> {
+        self.compare(Comparison::LessOrEqual, other)
+    }
+}
+
+impl<T: Field> From<T> for Expr<T> {
+    fn from(field: T) -> Self {
+        Expr::Field(field)
+    }
+}
+
+impl<T: Field> From<Literal> for Expr<T> {
+    fn from(literal: Literal) -> Self {
+        Expr::Literal(literal)
+    }
+}
+
+impl<T: Field> From<&str> for Expr<T> {
+    fn from(value: &str) -> Self {
+        Expr::literal(value)
+    }
+}
+
+impl<T: Field> From<String> for Expr<T> {
+    fn from(value: String) -> Self {
+        Expr::literal(value)
+    }
+}
+
+impl<T: Field> From<f64> for Expr<T> {
+    fn from(value: f64) -> Self {
+        Expr::literal(value)
+    }
+}
+
+impl<T: Field> From<i64> for Expr<T> {
+    fn from(value: i64) -> Self {
+        Expr::literal(value)
+    }
+}
+
+impl<T: Field> From<i32> for Expr<T> {
+    fn from(value: i32) -> Self {
+        Expr::literal(value)
+    }
+}
+
+impl<T: Field> From<Date> for Expr<T> {
+    fn from(value: Date) -> Self {
+        Expr::literal(value)
+    }
+}
+
+impl<T: Field> From<OffsetDateTime> for Expr<T> {
+    fn from(value: OffsetDateTime) -> Self {
+        Expr::literal(value)
+    }
+}
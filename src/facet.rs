@@ -0,0 +1,53 @@
+#![allow(dead_code)]
+use crate::client::{Client, Portal, Query};
+use crate::common::{Field, Order, Refine};
+use crate::error::Error;
+use crate::filter::Filter;
+use serde::{Deserialize, Serialize};
+
+/// Distinct value of a facet and the number of matching records.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct FacetValue {
+    pub name: String,
+    pub value: String,
+    pub count: u64,
+    /// `displayed`, `refined` or `excluded`
+    pub state: String,
+}
+
+#[derive(Deserialize)]
+struct Facets {
+    facets: Vec<Facet>,
+}
+
+#[derive(Deserialize)]
+struct Facet {
+    name: String,
+    facets: Vec<FacetValue>,
+}
+
+impl Client {
+    pub(crate) async fn dataset_facets<F: Field>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        field: F,
+        refine: Refine<F>,
+        filter: Option<Filter<F>>,
+    ) -> Result<Vec<FacetValue>, Error> {
+        let name = field.name();
+        let query = Query::new(Order::new(), filter).refine(refine);
+        let url = self.dataset_url(portal, dataset, "facets")?;
+        let mut params = query.params()?;
+        params.push(("facet", name.to_string()));
+        let url = reqwest::Url::parse_with_params(&url, &params)?;
+        let facets: Facets = self.fetch(portal, dataset, url).await?;
+        let values = facets
+            .facets
+            .into_iter()
+            .find(|facet| facet.name == name)
+            .map(|facet| facet.facets)
+            .unwrap_or_default();
+        Ok(values)
+    }
+}
@@ -0,0 +1,626 @@
+#![allow(private_bounds)]
+use crate::common::{non_finite, quote_field, quote_text, Field, GeoPoint2d, Literal};
+use crate::expr::{lookup, Expr};
+use geojson::GeoJson;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+use std::fmt;
+use time::OffsetDateTime;
+
+/// Operator of [`Filter::Compare`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+impl Comparison {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+        }
+    }
+
+    fn holds(self, ordering: Option<Ordering>) -> bool {
+        match self {
+            Comparison::Equal => ordering == Some(Ordering::Equal),
+            Comparison::NotEqual => matches!(ordering, Some(Ordering::Less | Ordering::Greater)),
+            Comparison::Greater => ordering == Some(Ordering::Greater),
+            Comparison::GreaterOrEqual => {
+                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+            }
+            Comparison::Less => ordering == Some(Ordering::Less),
+            Comparison::LessOrEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        }
+    }
+}
+
+/// Condition on the records of a dataset.
+///
+/// Renders to the ODSQL `where` parameter through `Display`, and can be evaluated
+/// against records in memory with [`Filter::matches`].
+///
+/// Build filters on a field through [`TypedField`](crate::TypedField), e.g.
+/// `fields::STATUS.equal("frei")`, which only accepts values of the field's type. For fields
+/// of dynamic datasets, wrap them with `TypedField::new`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter<T: Field> {
+    Compare(Expr<T>, Comparison, Expr<T>),
+    IsNull(Expr<T>),
+    /// Equal to one of the values. Never matches if there are none.
+    In(Expr<T>, Vec<Literal>),
+    /// Pattern where `%` stands for any sequence of characters.
+    Like(Expr<T>, String),
+    StartsWith(Expr<T>, String),
+    Search(Expr<T>, String),
+    /// Within the given number of meters of a point.
+    WithinDistance(T, GeoPoint2d, f64),
+    /// Within the rectangle spanned by two opposite corners.
+    InBbox(T, GeoPoint2d, GeoPoint2d),
+    /// Within the polygon through the points, closed automatically.
+    InPolygon(T, Vec<GeoPoint2d>),
+    Intersects(T, Box<GeoJson>),
+    /// All of the filters hold. `and` flattens chains into a single node, so long chains
+    /// neither nest nor recurse once per term.
+    And(Vec<Filter<T>>),
+    /// At least one of the filters holds, flattened like `And`.
+    Or(Vec<Filter<T>>),
+    Not(Box<Filter<T>>),
+}
+
+impl<T: Field> Filter<T> {
+    pub fn is_null(field: T) -> Self {
+        Filter::IsNull(Expr::Field(field))
+    }
+
+    pub fn is_not_null(field: T) -> Self {
+        Filter::is_null(field).not()
+    }
+
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::equal`")]
+    pub fn equal_str(field: T, value: &str) -> Self {
+        Self::compare(field, Comparison::Equal, value.into())
+    }
+
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::equal`")]
+    pub fn equal_num(field: T, value: f64) -> Self {
+        Self::compare(field, Comparison::Equal, value.into())
+    }
+
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::not_equal`")]
+    pub fn not_equal_str(field: T, value: &str) -> Self {
+        Self::compare(field, Comparison::NotEqual, value.into())
+    }
+
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::not_equal`")]
+    pub fn not_equal_num(field: T, value: f64) -> Self {
+        Self::compare(field, Comparison::NotEqual, value.into())
+    }
+
+    fn compare(field: T, comparison: Comparison, value: Literal) -> Self {
+        Filter::Compare(Expr::Field(field), comparison, Expr::Literal(value))
+    }
+
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::equal`")]
+    pub fn equal(field: T, value: impl Into<Literal>) -> Self {
+        Self::compare(field, Comparison::Equal, value.into())
+    }
+
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::not_equal`")]
+    pub fn not_equal(field: T, value: impl Into<Literal>) -> Self {
+        Self::compare(field, Comparison::NotEqual, value.into())
+    }
+
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::greater`")]
+    pub fn greater(field: T, value: impl Into<Literal>) -> Self {
+        Self::compare(field, Comparison::Greater, value.into())
+    }
+
+    #[deprecated(
+        note = "accepts values of any type on any field, use `TypedField::greater_or_equal`"
+    )]
+    pub fn greater_or_equal(field: T, value: impl Into<Literal>) -> Self {
+        Self::compare(field, Comparison::GreaterOrEqual, value.into())
+    }
+
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::less`")]
+    pub fn less(field: T, value: impl Into<Literal>) -> Self {
+        Self::compare(field, Comparison::Less, value.into())
+    }
+
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::less_or_equal`")]
+    pub fn less_or_equal(field: T, value: impl Into<Literal>) -> Self {
+        Self::compare(field, Comparison::LessOrEqual, value.into())
+    }
+
+    /// `field` is equal to one of `values`. Never matches if `values` is empty.
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::is_in`")]
+    pub fn is_in<V: Into<Literal>>(field: T, values: impl IntoIterator<Item = V>) -> Self {
+        let values = values.into_iter().map(Into::into).collect();
+        Filter::In(Expr::Field(field), values)
+    }
+
+    /// `low <= field <= high`
+    #[deprecated(note = "accepts values of any type on any field, use `TypedField::between`")]
+    pub fn between(field: T, low: impl Into<Literal>, high: impl Into<Literal>) -> Self
+    where
+        T: Clone,
+    {
+        let low = Self::compare(field.clone(), Comparison::GreaterOrEqual, low.into());
+        low.and(Self::compare(field, Comparison::LessOrEqual, high.into()))
+    }
+
+    /// Text pattern match where `%` stands for any sequence of characters.
+    #[deprecated(note = "accepts fields of any type, use `TypedField::like`")]
+    pub fn like(field: T, pattern: &str) -> Self {
+        Filter::Like(Expr::Field(field), pattern.to_string())
+    }
+
+    #[deprecated(note = "accepts fields of any type, use `TypedField::starts_with`")]
+    pub fn starts_with(field: T, value: &str) -> Self {
+        Filter::StartsWith(Expr::Field(field), value.to_string())
+    }
+
+    #[deprecated(note = "accepts fields of any type, use `TypedField::search`")]
+    pub fn search(field: T, value: &str) -> Self {
+        Filter::Search(Expr::Field(field), value.to_string())
+    }
+
+    /// `field` is at most `meters` away from `center`.
+    #[deprecated(note = "accepts fields of any type, use `TypedField::within_distance`")]
+    pub fn within_distance(field: T, center: GeoPoint2d, meters: f64) -> Self {
+        Filter::WithinDistance(field, center, meters)
+    }
+
+    /// `field` lies within the rectangle spanned by two opposite corners.
+    #[deprecated(note = "accepts fields of any type, use `TypedField::in_bbox`")]
+    pub fn in_bbox(field: T, corner: GeoPoint2d, opposite: GeoPoint2d) -> Self {
+        Filter::InBbox(field, corner, opposite)
+    }
+
+    /// `field` lies within the polygon through `points`, which is closed automatically.
+    #[deprecated(note = "accepts fields of any type, use `TypedField::in_polygon`")]
+    pub fn in_polygon(field: T, points: &[GeoPoint2d]) -> Self {
+        Filter::InPolygon(field, points.to_vec())
+    }
+
+    /// `field` shares at least one point with `geometry`.
+    #[deprecated(note = "accepts fields of any type, use `TypedField::intersects`")]
+    pub fn intersects(field: T, geometry: &GeoJson) -> Self {
+        Filter::Intersects(field, Box::new(geometry.clone()))
+    }
+
+    pub fn and(self, other: Self) -> Self {
+        let mut filters = match self {
+            Filter::And(filters) => filters,
+            filter => vec![filter],
+        };
+        match other {
+            Filter::And(more) => filters.extend(more),
+            filter => filters.push(filter),
+        }
+        Filter::And(filters)
+    }
+
+    pub fn or(self, other: Self) -> Self {
+        let mut filters = match self {
+            Filter::Or(filters) => filters,
+            filter => vec![filter],
+        };
+        match other {
+            Filter::Or(more) => filters.extend(more),
+            filter => filters.push(filter),
+        }
+        Filter::Or(filters)
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Self {
+        Filter::Not(Box::new(self))
+    }
+
+    /// Evaluate the filter against a record in memory, e.g. one from a cache or an export.
+    ///
+    /// As in ODSQL, no comparison holds for null, so neither `!=` nor `not (x = 1)` match
+    /// records without the value. The evaluation follows the ODSQL documentation and has not
+    /// been compared with responses of the portals yet. Known differences:
+    ///
+    /// - `like` and `search` ignore case, `startswith` doesn't.
+    /// - `search` matches any part of the text, while the portal matches the words of its
+    ///   full-text index and ignores accents too.
+    /// - Relative dates are taken from the current time.
+    /// - Geographic conditions are checked on the points of a geometry, so shapes that only
+    ///   cross each other between two points don't intersect.
+    pub fn matches<R: Serialize>(&self, record: &R) -> bool {
+        match serde_json::to_value(record) {
+            Ok(Value::Object(record)) => {
+                self.evaluate(&record, OffsetDateTime::now_utc()) == Some(true)
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn invalid(&self) -> Option<String> {
+        let points = |points: &[GeoPoint2d]| {
+            non_finite(points.iter().flat_map(|point| [point.lat, point.lon]))
+        };
+        match self {
+            Filter::Compare(left, _, right) => left.invalid().or_else(|| right.invalid()),
+            Filter::IsNull(expression)
+            | Filter::Like(expression, _)
+            | Filter::StartsWith(expression, _)
+            | Filter::Search(expression, _) => expression.invalid(),
+            Filter::In(expression, values) => expression
+                .invalid()
+                .or_else(|| values.iter().find_map(Literal::invalid)),
+            Filter::WithinDistance(_, center, meters) => {
+                non_finite([center.lat, center.lon, *meters])
+                    .or_else(|| meters.is_sign_negative().then(|| format!("{meters}m")))
+            }
+            Filter::InBbox(_, corner, opposite) => points(&[*corner, *opposite]),
+            Filter::InPolygon(_, polygon) => points(polygon),
+            Filter::Intersects(..) => None,
+            Filter::And(filters) | Filter::Or(filters) => filters.iter().find_map(Filter::invalid),
+            Filter::Not(filter) => filter.invalid(),
+        }
+    }
+
+    /// Whether the filter holds for `record`, or `None` if that is unknown because a value it
+    /// depends on is null. As in SQL, `not` keeps an unknown result unknown, and `and` and `or`
+    /// only need one side when it decides the result.
+    pub(crate) fn evaluate(
+        &self,
+        record: &Map<String, Value>,
+        now: OffsetDateTime,
+    ) -> Option<bool> {
+        let geo = |field: &T, condition: &dyn Fn(&Value) -> bool| {
+            let value = lookup(record, field.name());
+            (!value.is_null()).then(|| condition(value))
+        };
+        match self {
+            Filter::Compare(left, comparison, right) => {
+                let left = left.evaluate(record, now);
+                let right = right.evaluate(record, now);
+                if left.is_null() || right.is_null() {
+                    return None;
+                }
+                Some(comparison.holds(left.compare(&right)))
+            }
+            Filter::IsNull(expression) => Some(expression.evaluate(record, now).is_null()),
+            Filter::In(expression, values) => {
+                let value = expression.evaluate(record, now);
+                if value.is_null() {
+                    return None;
+                }
+                Some(
+                    values
+                        .iter()
+                        .any(|literal| value.compare(&literal.into()) == Some(Ordering::Equal)),
+                )
+            }
+            Filter::Like(expression, pattern) => {
+                expression.evaluate(record, now).text().map(|text| {
+                    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+                    let text: Vec<char> = text.to_lowercase().chars().collect();
+                    like(&text, &pattern)
+                })
+            }
+            Filter::StartsWith(expression, prefix) => expression
+                .evaluate(record, now)
+                .text()
+                .map(|text| text.starts_with(prefix.as_str())),
+            Filter::Search(expression, value) => expression
+                .evaluate(record, now)
+                .text()
+                .map(|text| text.to_lowercase().contains(&value.to_lowercase())),
+            Filter::WithinDistance(field, center, meters) => {
+                let center = (center.lon, center.lat);
+                geo(field, &|value| {
+                    all(&points(value), |point| distance(point, center) <= *meters)
+                })
+            }
+            Filter::InBbox(field, corner, opposite) => {
+                let (west, east) = (corner.lon.min(opposite.lon), corner.lon.max(opposite.lon));
+                let (south, north) = (corner.lat.min(opposite.lat), corner.lat.max(opposite.lat));
+                geo(field, &|value| {
+                    all(&points(value), |(lon, lat)| {
+                        (west..=east).contains(&lon) && (south..=north).contains(&lat)
+                    })
+                })
+            }
+            Filter::InPolygon(field, polygon) => {
+                let ring: Vec<Point> = polygon.iter().map(|point| (point.lon, point.lat)).collect();
+                geo(field, &|value| {
+                    all(&points(value), |point| inside(point, &ring))
+                })
+            }
+            Filter::Intersects(field, geometry) => {
+                let geometry = serde_json::to_value(geometry).unwrap_or_default();
+                geo(field, &|value| intersects(value, &geometry))
+            }
+            Filter::And(filters) => {
+                let results: Vec<_> = filters
+                    .iter()
+                    .map(|filter| filter.evaluate(record, now))
+                    .collect();
+                if results.contains(&Some(false)) {
+                    Some(false)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(true)
+                }
+            }
+            Filter::Or(filters) => {
+                let results: Vec<_> = filters
+                    .iter()
+                    .map(|filter| filter.evaluate(record, now))
+                    .collect();
+                if results.contains(&Some(true)) {
+                    Some(true)
+                } else if results.contains(&None) {
+                    None
+                } else {
+                    Some(false)
+                }
+            }
+            Filter::Not(filter) => filter.evaluate(record, now).map(|holds| !holds),
+        }
+    }
+}
+
+impl<T: Field> fmt::Display for Filter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Compare(left, comparison, right) => write!(
+                f,
+                "{} {} {}",
+                left.render(),
+                comparison.symbol(),
+                right.render()
+            ),
+            Filter::IsNull(expression) => write!(f, "{} is null", expression.render()),
+            Filter::In(expression, values) => {
+                let expression = expression.render();
+                if values.is_empty() {
+                    return write!(f, "({expression} is null) and ({expression} is not null)");
+                }
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|value| format!("{expression} = {}", value.render()))
+                    .collect();
+                write!(f, "({})", values.join(") or ("))
+            }
+            Filter::Like(expression, pattern) => {
+                write!(f, "{} like {}", expression.render(), quote_text(pattern))
+            }
+            Filter::StartsWith(expression, value) => {
+                write!(
+                    f,
+                    "startswith({}, {})",
+                    expression.render(),
+                    quote_text(value)
+                )
+            }
+            Filter::Search(expression, value) => {
+                write!(f, "search({}, {})", expression.render(), quote_text(value))
+            }
+            Filter::WithinDistance(field, center, meters) => write!(
+                f,
+                "within_distance({}, {}, {meters}m)",
+                quote_field(field.name()),
+                geom(&wkt_point(*center))
+            ),
+            Filter::InBbox(field, corner, opposite) => write!(
+                f,
+                "in_bbox({}, {}, {}, {}, {})",
+                quote_field(field.name()),
+                corner.lat,
+                corner.lon,
+                opposite.lat,
+                opposite.lon
+            ),
+            Filter::InPolygon(field, points) => {
+                let mut ring: Vec<String> = points
+                    .iter()
+                    .map(|point| format!("{} {}", point.lon, point.lat))
+                    .collect();
+                if let (Some(first), Some(last)) = (ring.first(), ring.last()) {
+                    if first != last {
+                        ring.push(first.clone());
+                    }
+                }
+                let polygon = format!("POLYGON(({}))", ring.join(", "));
+                write!(
+                    f,
+                    "within({}, {})",
+                    quote_field(field.name()),
+                    geom(&polygon)
+                )
+            }
+            Filter::Intersects(field, geometry) => write!(
+                f,
+                "intersects({}, {})",
+                quote_field(field.name()),
+                geom(&geometry.to_string())
+            ),
+            Filter::And(filters) => chain(f, filters, " and "),
+            Filter::Or(filters) => chain(f, filters, " or "),
+            Filter::Not(filter) => match filter.as_ref() {
+                Filter::IsNull(expression) => write!(f, "{} is not null", expression.render()),
+                filter => write!(f, "not ({filter})"),
+            },
+        }
+    }
+}
+
+/// Operands of `and` or `or` in parentheses, without nesting the chain.
+fn chain<T: Field>(
+    f: &mut fmt::Formatter<'_>,
+    filters: &[Filter<T>],
+    operator: &str,
+) -> fmt::Result {
+    for (index, filter) in filters.iter().enumerate() {
+        if index > 0 {
+            f.write_str(operator)?;
+        }
+        write!(f, "({filter})")?;
+    }
+    Ok(())
+}
+
+/// ODSQL geometry literal from WKT or GeoJSON.
+pub(crate) fn geom(value: &str) -> String {
+    format!("geom'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+pub(crate) fn wkt_point(point: GeoPoint2d) -> String {
+    format!("POINT({} {})", point.lon, point.lat)
+}
+
+/// `%` matches any sequence of characters, everything else itself.
+///
+/// Greedy: on a mismatch only the most recent `%` takes one more character, which is
+/// enough because any earlier `%` could only pass that character on to it. O(n·m).
+fn like(text: &[char], pattern: &[char]) -> bool {
+    let (mut at, mut next) = (0, 0);
+    // Position after the last `%` and the text position it currently matches up to.
+    let mut wildcard = None;
+    while at < text.len() {
+        match pattern.get(next) {
+            Some('%') => {
+                next += 1;
+                wildcard = Some((next, at));
+            }
+            Some(expected) if *expected == text[at] => {
+                next += 1;
+                at += 1;
+            }
+            _ => match wildcard {
+                Some((after, matched)) => {
+                    next = after;
+                    at = matched + 1;
+                    wildcard = Some((after, at));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[next..].iter().all(|&c| c == '%')
+}
+
+/// Longitude and latitude.
+pub(crate) type Point = (f64, f64);
+
+/// Points of a `GeoPoint2d` or of any GeoJSON value.
+pub(crate) fn points(value: &Value) -> Vec<Point> {
+    fn collect(value: &Value, points: &mut Vec<Point>) {
+        match value {
+            Value::Object(object) => {
+                let lat = object.get("lat").and_then(Value::as_f64);
+                let lon = object.get("lon").and_then(Value::as_f64);
+                if let (Some(lat), Some(lon)) = (lat, lon) {
+                    points.push((lon, lat));
+                    return;
+                }
+                for key in ["coordinates", "geometry", "geometries", "features"] {
+                    if let Some(value) = object.get(key) {
+                        collect(value, points);
+                    }
+                }
+            }
+            Value::Array(items) => match items.as_slice() {
+                [Value::Number(lon), Value::Number(lat), ..] => {
+                    if let (Some(lon), Some(lat)) = (lon.as_f64(), lat.as_f64()) {
+                        points.push((lon, lat));
+                    }
+                }
+                items => items.iter().for_each(|item| collect(item, points)),
+            },
+            _ => {}
+        }
+    }
+    let mut points = Vec::new();
+    collect(value, &mut points);
+    points
+}
+
+/// Outer rings of the polygons in a GeoJSON value.
+fn polygons(value: &Value) -> Vec<Vec<Point>> {
+    fn collect(value: &Value, rings: &mut Vec<Vec<Point>>) {
+        let Value::Object(object) = value else {
+            if let Value::Array(items) = value {
+                items.iter().for_each(|item| collect(item, rings));
+            }
+            return;
+        };
+        let coordinates = object.get("coordinates").and_then(Value::as_array);
+        match (object.get("type").and_then(Value::as_str), coordinates) {
+            (Some("Polygon"), Some(polygon)) => rings.extend(polygon.first().map(points)),
+            (Some("MultiPolygon"), Some(polygons)) => rings.extend(
+                polygons
+                    .iter()
+                    .filter_map(|polygon| polygon.as_array()?.first().map(points)),
+            ),
+            _ => {
+                for key in ["geometry", "geometries", "features"] {
+                    if let Some(value) = object.get(key) {
+                        collect(value, rings);
+                    }
+                }
+            }
+        }
+    }
+    let mut rings = Vec::new();
+    collect(value, &mut rings);
+    rings
+}
+
+/// `condition` holds for every point, of which there is at least one.
+fn all(points: &[Point], condition: impl Fn(Point) -> bool) -> bool {
+    !points.is_empty() && points.iter().all(|point| condition(*point))
+}
+
+/// Great-circle distance in meters.
+pub(crate) fn distance((lon1, lat1): Point, (lon2, lat2): Point) -> f64 {
+    const EARTH_RADIUS: f64 = 6_371_008.8;
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (lon2 - lon1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * a.sqrt().asin()
+}
+
+/// Ray casting test of `point` against a ring.
+fn inside((x, y): Point, ring: &[Point]) -> bool {
+    let mut inside = false;
+    for (index, &(x1, y1)) in ring.iter().enumerate() {
+        let (x2, y2) = ring[(index + 1) % ring.len()];
+        if (y1 > y) != (y2 > y) && x < (x2 - x1) * (y - y1) / (y2 - y1) + x1 {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+fn intersects(left: &Value, right: &Value) -> bool {
+    let (left_points, right_points) = (points(left), points(right));
+    let contains = |points: &[Point], rings: &[Vec<Point>]| {
+        points
+            .iter()
+            .any(|point| rings.iter().any(|ring| inside(*point, ring)))
+    };
+    left_points.iter().any(|point| right_points.contains(point))
+        || contains(&left_points, &polygons(right))
+        || contains(&right_points, &polygons(left))
+}
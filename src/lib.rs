@@ -1,4 +1,40 @@
+mod aggregate;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod cache;
+pub mod catalog;
+mod client;
 mod common;
+mod dataset;
+pub mod dynamic;
+mod error;
+mod export;
+mod expr;
+mod facet;
+mod filter;
+mod metadata;
+#[cfg(feature = "testing")]
+mod mock;
+mod parse;
+mod registry;
+mod retry;
+mod stream;
+pub use crate::aggregate::*;
+pub use crate::cache::*;
+pub use crate::client::*;
 pub use crate::common::*;
+pub use crate::dataset::*;
+pub use crate::error::*;
+pub use crate::export::*;
+pub use crate::expr::*;
+pub use crate::facet::*;
+pub use crate::filter::*;
+pub use crate::metadata::*;
+pub use crate::parse::*;
+pub use crate::registry::*;
+pub use crate::retry::*;
+pub use crate::stream::*;
 pub mod land;
 pub mod stadt;
+#[cfg(feature = "testing")]
+pub mod testing;
@@ -0,0 +1,99 @@
+use crate::client::{Client, Portal};
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// Catalog entry of a dataset as published by the portal.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(from = "RawMetadata")]
+pub struct Metadata {
+    pub dataset_id: String,
+    pub title: Option<String>,
+    /// HTML description
+    pub description: Option<String>,
+    /// Last modification of the dataset.
+    pub modified: Option<OffsetDateTime>,
+    /// Last time the records were processed by the portal.
+    pub data_processed: Option<OffsetDateTime>,
+    /// Last time the metadata was processed by the portal.
+    pub metadata_processed: Option<OffsetDateTime>,
+    pub publisher: Option<String>,
+    pub license: Option<String>,
+    pub license_url: Option<String>,
+    pub keywords: Vec<String>,
+    pub themes: Vec<String>,
+    pub records_count: Option<u64>,
+    pub fields: Vec<FieldDefinition>,
+}
+
+/// Live schema of a single field.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct FieldDefinition {
+    pub name: String,
+    pub label: Option<String>,
+    /// Opendatasoft type, e.g. `text`, `int`, `double`, `date`, `datetime` or `geo_point_2d`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawMetadata {
+    dataset_id: String,
+    #[serde(default)]
+    fields: Vec<FieldDefinition>,
+    metas: RawMetas,
+}
+
+#[derive(Deserialize)]
+struct RawMetas {
+    default: RawDefault,
+}
+
+#[derive(Deserialize)]
+struct RawDefault {
+    title: Option<String>,
+    description: Option<String>,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    modified: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    data_processed: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    metadata_processed: Option<OffsetDateTime>,
+    publisher: Option<String>,
+    license: Option<String>,
+    license_url: Option<String>,
+    keyword: Option<Vec<String>>,
+    theme: Option<Vec<String>>,
+    records_count: Option<u64>,
+}
+
+impl From<RawMetadata> for Metadata {
+    fn from(raw: RawMetadata) -> Self {
+        let meta = raw.metas.default;
+        Metadata {
+            dataset_id: raw.dataset_id,
+            title: meta.title,
+            description: meta.description,
+            modified: meta.modified,
+            data_processed: meta.data_processed,
+            metadata_processed: meta.metadata_processed,
+            publisher: meta.publisher,
+            license: meta.license,
+            license_url: meta.license_url,
+            keywords: meta.keyword.unwrap_or_default(),
+            themes: meta.theme.unwrap_or_default(),
+            records_count: meta.records_count,
+            fields: raw.fields,
+        }
+    }
+}
+
+impl Client {
+    /// Fetch the catalog entry of any dataset, compiled in or not.
+    pub async fn dataset_metadata(&self, portal: Portal, dataset: &str) -> Result<Metadata, Error> {
+        let url = self.dataset_url(portal, dataset, "")?;
+        let url = reqwest::Url::parse(&url)?;
+        self.fetch(portal, dataset, url).await
+    }
+}
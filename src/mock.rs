@@ -0,0 +1,442 @@
+use crate::client::{Client, Portal};
+use crate::common::{Direction, Order, Sort};
+use crate::dynamic::Field;
+use crate::expr::Scalar;
+use crate::filter::Filter;
+use crate::stream::MAX_OFFSET;
+use serde_json::{json, Map, Value};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::atomic::{self, AtomicBool, AtomicUsize};
+use std::sync::{Arc, Mutex};
+use time::OffsetDateTime;
+
+type Record = Map<String, Value>;
+
+/// In-process Opendatasoft server answering the records and exports endpoints from local data.
+///
+/// `where` and `order_by` are evaluated with [`Filter::parse`] and [`Order::parse`], so the
+/// subset of ODSQL the client can build is supported, including `limit`, `offset`, `select`
+/// with plain field names and `total_count`. Unknown datasets, invalid ODSQL and pages beyond
+/// the offset ceiling get the error responses of the real portal.
+///
+/// Filters are evaluated like [`Filter::matches`], so where that differs from the portal,
+/// so does the server. Tests against it check the requests and decoding, not ODSQL itself.
+///
+/// Both portals are served from the same datasets. The server stops when it is dropped.
+#[derive(Debug)]
+pub struct MockServer {
+    url: String,
+    state: Arc<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    datasets: Mutex<HashMap<String, Vec<Record>>>,
+    /// Statuses answered instead of the next requests, for error paths.
+    failures: Mutex<Vec<u16>>,
+    requests: AtomicUsize,
+    stopped: AtomicBool,
+}
+
+impl MockServer {
+    /// Start a server on a free port of localhost.
+    pub fn start() -> io::Result<Self> {
+        Self::bind("127.0.0.1:0")
+    }
+
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(State::default());
+        let server = state.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server.stopped.load(atomic::Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let state = server.clone();
+                std::thread::spawn(move || {
+                    let _ = state.serve(stream);
+                });
+            }
+        });
+        Ok(MockServer { url, state })
+    }
+
+    /// Base URL, e.g. `http://127.0.0.1:41234`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Client with both portals pointed at this server.
+    pub fn client(&self) -> Client {
+        Client::new()
+            .base_url(Portal::Stadt, &self.url)
+            .base_url(Portal::Land, &self.url)
+    }
+
+    /// Serve `records`, which must be JSON objects, as dataset `id`.
+    pub fn dataset(&self, id: &str, records: impl IntoIterator<Item = Value>) -> &Self {
+        let records = records
+            .into_iter()
+            .filter_map(|record| match record {
+                Value::Object(record) => Some(record),
+                _ => None,
+            })
+            .collect();
+        self.state.datasets().insert(id.to_string(), records);
+        self
+    }
+
+    /// Serve a file as dataset `id`: a `.json` array, `.jsonl` lines or a `;` separated `.csv`.
+    ///
+    /// CSV values that parse as numbers become numbers and empty ones become null.
+    pub fn load(&self, id: &str, path: impl AsRef<Path>) -> io::Result<&Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let invalid = |error: &dyn std::fmt::Display| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {error}", path.display()),
+            )
+        };
+        let records = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::from_str(&text).map_err(|error| invalid(&error))?,
+            Some("jsonl") => text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(serde_json::from_str)
+                .collect::<Result<_, _>>()
+                .map_err(|error| invalid(&error))?,
+            Some("csv") => csv_records(&text).map_err(|error| invalid(&error))?,
+            _ => return Err(invalid(&"expected a .json, .jsonl or .csv file")),
+        };
+        Ok(self.dataset(id, records))
+    }
+
+    /// Serve every `.json`, `.jsonl` and `.csv` file in `dir` as the dataset named like the file.
+    pub fn load_dir(&self, dir: impl AsRef<Path>) -> io::Result<&Self> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            let id = path.file_stem().and_then(|stem| stem.to_str());
+            if let (Some("json" | "jsonl" | "csv"), Some(id)) = (extension, id) {
+                self.load(id, &path)?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Answer the next `times` requests with `status` and an Opendatasoft error body.
+    pub fn fail(&self, status: u16, times: usize) -> &Self {
+        self.state
+            .failures()
+            .extend(std::iter::repeat_n(status, times));
+        self
+    }
+
+    /// Number of requests received so far.
+    pub fn requests(&self) -> usize {
+        self.state.requests.load(atomic::Ordering::SeqCst)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.state.stopped.store(true, atomic::Ordering::SeqCst);
+        // Wake up the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.url.trim_start_matches("http://"));
+    }
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+fn error(status: u16, error_code: &str, message: &str) -> Response {
+    let body = json!({ "status_code": status, "error_code": error_code, "message": message });
+    Response {
+        status,
+        content_type: "application/json; charset=utf-8",
+        body: body.to_string(),
+    }
+}
+
+impl State {
+    fn datasets(&self) -> std::sync::MutexGuard<'_, HashMap<String, Vec<Record>>> {
+        self.datasets
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn failures(&self) -> std::sync::MutexGuard<'_, Vec<u16>> {
+        self.failures
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn serve(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let target = line.split_whitespace().nth(1).unwrap_or("/").to_string();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+        if self.stopped.load(atomic::Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.requests.fetch_add(1, atomic::Ordering::SeqCst);
+
+        let failure = {
+            let mut failures = self.failures();
+            (!failures.is_empty()).then(|| failures.remove(0))
+        };
+        let response = match failure {
+            Some(status) => error(status, "MockFailure", "failure requested by the test"),
+            None => self.respond(&target),
+        };
+        let reason = reqwest::StatusCode::from_u16(response.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or("");
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 {} {reason}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.status,
+            response.content_type,
+            response.body.len()
+        )?;
+        stream.write_all(response.body.as_bytes())?;
+        stream.flush()
+    }
+
+    fn respond(&self, target: &str) -> Response {
+        let Ok(url) = url::Url::parse(&format!("http://localhost{target}")) else {
+            return error(400, "InvalidRESTParameterError", "invalid request");
+        };
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        let path = url
+            .path()
+            .strip_prefix("/api/explore/v2.1/catalog/datasets/");
+        let segments: Vec<_> = path.unwrap_or_default().split('/').collect();
+        let (id, format) = match segments.as_slice() {
+            [id, "records"] => (*id, None),
+            [id, "exports", format] => (*id, Some(*format)),
+            _ => return error(404, "NotFound", "unknown endpoint"),
+        };
+        let datasets = self.datasets();
+        let Some(records) = datasets.get(id) else {
+            return error(404, "NotFound", &format!("Unknown dataset: {id}"));
+        };
+
+        let number = |name: &str, default: i64| match params.get(name) {
+            Some(value) => value.parse::<i64>().map_err(|_| {
+                let message = format!("Invalid value for {name}: {value}");
+                error(400, "InvalidRESTParameterError", &message)
+            }),
+            None => Ok(default),
+        };
+        let (limit, offset) = match (
+            number("limit", if format.is_some() { -1 } else { 10 }),
+            number("offset", 0),
+        ) {
+            (Ok(limit), Ok(offset)) => (limit, offset.max(0) as usize),
+            (Err(response), _) | (_, Err(response)) => return response,
+        };
+        if format.is_none() && !(0..=100).contains(&limit) {
+            let message = format!("Invalid value for limit: {limit}, must be between 0 and 100");
+            return error(400, "InvalidRESTParameterError", &message);
+        }
+        if format.is_none() && offset as u64 + limit as u64 > MAX_OFFSET {
+            let message = format!("Invalid value for limit + offset, must be at most {MAX_OFFSET}");
+            return error(400, "InvalidRESTParameterError", &message);
+        }
+
+        let records = match query(records, &params) {
+            Ok(records) => records,
+            Err(message) => return error(400, "ODSQLError", &message),
+        };
+        let total_count = records.len();
+        let page = records
+            .into_iter()
+            .skip(offset)
+            .take(usize::try_from(limit).unwrap_or(usize::MAX));
+        let page = select(page, params.get("select").map(String::as_str));
+
+        let json = "application/json; charset=utf-8";
+        match format {
+            None => Response {
+                status: 200,
+                content_type: json,
+                body: json!({ "total_count": total_count, "results": page }).to_string(),
+            },
+            Some("json") => Response {
+                status: 200,
+                content_type: json,
+                body: Value::from(page).to_string(),
+            },
+            Some("jsonl") => Response {
+                status: 200,
+                content_type: "application/jsonl; charset=utf-8",
+                body: page
+                    .iter()
+                    .map(|record| format!("{}\n", Value::from(record.clone())))
+                    .collect(),
+            },
+            Some("csv") => Response {
+                status: 200,
+                content_type: "text/csv; charset=utf-8",
+                body: csv_body(&page),
+            },
+            Some(format) => error(
+                400,
+                "InvalidRESTParameterError",
+                &format!("Unknown format: {format}"),
+            ),
+        }
+    }
+}
+
+/// Records matching `where`, sorted by `order_by`.
+fn query(records: &[Record], params: &HashMap<String, String>) -> Result<Vec<Record>, String> {
+    let now = OffsetDateTime::now_utc();
+    let filter = match params.get("where") {
+        Some(filter) => Some(Filter::<Field>::parse(filter).map_err(|error| error.to_string())?),
+        None => None,
+    };
+    let order = match params.get("order_by") {
+        Some(order) => Order::<Field>::parse(order).map_err(|error| error.to_string())?,
+        None => Order::new(),
+    };
+    let mut records: Vec<_> = records
+        .iter()
+        .filter(|record| {
+            filter
+                .as_ref()
+                .is_none_or(|filter| filter.evaluate(record, now) == Some(true))
+        })
+        .map(|record| {
+            let keys: Vec<_> = order
+                .keys
+                .iter()
+                .map(|key| match key {
+                    Sort::By(expression, _) => expression.evaluate(record, now),
+                    Sort::Alias(alias, _) => Scalar::from_value(crate::expr::lookup(record, alias)),
+                    Sort::Random(seed) => {
+                        let record = Value::from(record.clone()).to_string();
+                        Scalar::Number(crate::cache::hash(&format!("{seed}{record}")) as f64)
+                    }
+                })
+                .collect();
+            (keys, record.clone())
+        })
+        .collect();
+    records.sort_by(|(left, _), (right, _)| {
+        order
+            .keys
+            .iter()
+            .zip(left.iter().zip(right))
+            .map(|(key, (left, right))| {
+                let ordering = compare(left, right);
+                match key {
+                    Sort::By(_, Direction::Descending) | Sort::Alias(_, Direction::Descending) => {
+                        ordering.reverse()
+                    }
+                    _ => ordering,
+                }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    Ok(records.into_iter().map(|(_, record)| record).collect())
+}
+
+/// Total order for sorting, with nulls first and incomparable values as equal.
+fn compare(left: &Scalar, right: &Scalar) -> Ordering {
+    match (left.is_null(), right.is_null()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => left.compare(right).unwrap_or(Ordering::Equal),
+    }
+}
+
+/// Keep only the fields listed in `select`, unless it is missing or `*`.
+fn select(records: impl Iterator<Item = Record>, select: Option<&str>) -> Vec<Record> {
+    let fields: Option<Vec<_>> = select.filter(|select| select.trim() != "*").map(|select| {
+        select
+            .split(',')
+            .map(|field| field.trim().trim_matches('`').to_string())
+            .collect()
+    });
+    records
+        .map(|record| match &fields {
+            Some(fields) => record
+                .into_iter()
+                .filter(|(name, _)| fields.contains(name))
+                .collect(),
+            None => record,
+        })
+        .collect()
+}
+
+fn csv_records(text: &str) -> Result<Vec<Value>, csv::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .from_reader(text.as_bytes());
+    let headers = reader.headers()?.clone();
+    reader
+        .records()
+        .map(|row| {
+            let row = row?;
+            let record: Record = headers
+                .iter()
+                .zip(row.iter())
+                .map(|(name, value)| {
+                    let value = match value {
+                        "" => Value::Null,
+                        value => value
+                            .parse::<i64>()
+                            .map(Value::from)
+                            .or_else(|_| value.parse::<f64>().map(Value::from))
+                            .unwrap_or_else(|_| Value::from(value)),
+                    };
+                    (name.to_string(), value)
+                })
+                .collect();
+            Ok(Value::Object(record))
+        })
+        .collect()
+}
+
+/// Semicolon separated records, with the columns in the alphabetical order of `Record`'s keys.
+fn csv_body(records: &[Record]) -> String {
+    let mut names: Vec<&String> = Vec::new();
+    for name in records.iter().flat_map(Map::keys) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b';')
+        .from_writer(Vec::new());
+    let _ = writer.write_record(&names);
+    for record in records {
+        let row = names.iter().map(|name| match record.get(*name) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(text)) => text.clone(),
+            Some(value) => value.to_string(),
+        });
+        let _ = writer.write_record(row);
+    }
+    String::from_utf8(writer.into_inner().unwrap_or_default()).unwrap_or_default()
+}
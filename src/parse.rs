@@ -0,0 +1,881 @@
+#![allow(private_bounds)]
+use crate::common::{Direction, Field, FieldType, GeoPoint2d, Literal, Order, Sort};
+use crate::expr::{Expr, Function, Scalar, Shift};
+use crate::filter::{Comparison, Filter};
+use geojson::{GeoJson, Geometry};
+use std::fmt;
+use std::iter::Peekable;
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::str::CharIndices;
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime};
+
+/// ODSQL `where` expression that could not be turned into a [`Filter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Byte range of the offending part of the input.
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    UnexpectedEnd,
+    UnexpectedToken(String),
+    /// A string or backticked field name is missing its closing quote.
+    Unterminated,
+    UnknownField(String),
+    UnknownFunction(String),
+    InvalidLiteral(String),
+    TypeMismatch {
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// Valid ODSQL that has no [`Filter`] counterpart.
+    Unsupported(&'static str),
+    /// Parentheses, `not` or function calls nested deeper than [`MAX_DEPTH`].
+    TooDeep,
+}
+
+/// How deeply parentheses, `not` and function calls may be nested in parsed input.
+pub const MAX_DEPTH: usize = 100;
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnexpectedEnd => write!(f, "unexpected end of filter")?,
+            ParseErrorKind::UnexpectedToken(token) => write!(f, "unexpected `{token}`")?,
+            ParseErrorKind::Unterminated => write!(f, "missing closing quote")?,
+            ParseErrorKind::UnknownField(name) => write!(f, "unknown field `{name}`")?,
+            ParseErrorKind::UnknownFunction(name) => write!(f, "unknown function `{name}`")?,
+            ParseErrorKind::InvalidLiteral(literal) => write!(f, "invalid literal `{literal}`")?,
+            ParseErrorKind::TypeMismatch { expected, found } => {
+                write!(f, "expected {expected}, found {found}")?
+            }
+            ParseErrorKind::Unsupported(what) => write!(f, "unsupported: {what}")?,
+            ParseErrorKind::TooDeep => write!(f, "nested more than {MAX_DEPTH} levels deep")?,
+        }
+        write!(f, " at {}..{}", self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl<T: Field> Filter<T> {
+    /// Parse an ODSQL `where` expression, resolving field names against `T`.
+    ///
+    /// Unknown fields and values of the wrong type are reported with their position in
+    /// `input`. The `Display` output of the result is the normalized form of `input`.
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser::new(input)?;
+        let filter = parser.or()?;
+        match parser.tokens.get(parser.position) {
+            Some((_, span)) => Err(parser.unexpected(span.clone())),
+            None => Ok(filter),
+        }
+    }
+}
+
+impl<T: Field> Order<T> {
+    /// Parse an ODSQL `order_by` expression, resolving field names against `T`.
+    ///
+    /// Names that are not fields of `T` are read as aliases. An empty `input` is the empty order.
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser::new(input)?;
+        let mut order = Order::new();
+        if parser.tokens.is_empty() {
+            return Ok(order);
+        }
+        loop {
+            order.keys.push(parser.sort()?);
+            match parser.tokens.get(parser.position) {
+                None => return Ok(order),
+                Some((Token::Comma, _)) => parser.position += 1,
+                Some((_, span)) => return Err(parser.unexpected(span.clone())),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Field(String),
+    Text(String),
+    Number(String),
+    Date(String),
+    Geom(String),
+    Operator(Comparison),
+    Open,
+    Close,
+    Comma,
+    Minus,
+}
+
+fn error(kind: ParseErrorKind, span: Range<usize>) -> ParseError {
+    ParseError { kind, span }
+}
+
+/// Content of a quoted string whose opening quote was consumed, with `\` escapes resolved.
+fn quoted(chars: &mut Peekable<CharIndices>, quote: char) -> Option<String> {
+    let mut text = String::new();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => text.push(chars.next()?.1),
+            c if c == quote => return Some(text),
+            c => text.push(c),
+        }
+    }
+    None
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token, Range<usize>)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    let word = |c: char| c.is_alphanumeric() || c == '_';
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        chars.next();
+        let token = match c {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '-' => Token::Minus,
+            '=' => Token::Operator(Comparison::Equal),
+            '!' if chars.next_if(|&(_, c)| c == '=').is_some() => {
+                Token::Operator(Comparison::NotEqual)
+            }
+            '<' if chars.next_if(|&(_, c)| c == '=').is_some() => {
+                Token::Operator(Comparison::LessOrEqual)
+            }
+            '<' if chars.next_if(|&(_, c)| c == '>').is_some() => {
+                Token::Operator(Comparison::NotEqual)
+            }
+            '<' => Token::Operator(Comparison::Less),
+            '>' if chars.next_if(|&(_, c)| c == '=').is_some() => {
+                Token::Operator(Comparison::GreaterOrEqual)
+            }
+            '>' => Token::Operator(Comparison::Greater),
+            '`' | '"' | '\'' => {
+                let text = quoted(&mut chars, c)
+                    .ok_or_else(|| error(ParseErrorKind::Unterminated, start..input.len()))?;
+                if c == '`' {
+                    Token::Field(text)
+                } else {
+                    Token::Text(text)
+                }
+            }
+            c if c.is_ascii_digit() => {
+                while chars
+                    .next_if(|&(_, c)| c.is_ascii_digit() || c == '.')
+                    .is_some()
+                {}
+                let mut exponent = chars.clone();
+                if exponent.next_if(|&(_, c)| c == 'e' || c == 'E').is_some() {
+                    exponent.next_if(|&(_, c)| c == '+' || c == '-');
+                    if exponent.next_if(|&(_, c)| c.is_ascii_digit()).is_some() {
+                        while exponent.next_if(|&(_, c)| c.is_ascii_digit()).is_some() {}
+                        chars = exponent;
+                    }
+                }
+                let mut number = true;
+                while chars.next_if(|&(_, c)| word(c) || c == '.').is_some() {
+                    number = false;
+                }
+                let end = chars.peek().map_or(input.len(), |&(end, _)| end);
+                let text = input[start..end].to_string();
+                if number {
+                    Token::Number(text)
+                } else {
+                    Token::Word(text)
+                }
+            }
+            c if word(c) => {
+                while chars.next_if(|&(_, c)| word(c)).is_some() {}
+                let end = chars.peek().map_or(input.len(), |&(end, _)| end);
+                let text = &input[start..end];
+                let literal =
+                    text.eq_ignore_ascii_case("date") || text.eq_ignore_ascii_case("geom");
+                if literal && chars.next_if(|&(_, c)| c == '\'').is_some() {
+                    let value = quoted(&mut chars, '\'')
+                        .ok_or_else(|| error(ParseErrorKind::Unterminated, start..input.len()))?;
+                    if text.eq_ignore_ascii_case("date") {
+                        Token::Date(value)
+                    } else {
+                        Token::Geom(value)
+                    }
+                } else {
+                    Token::Word(text.to_string())
+                }
+            }
+            c => {
+                let end = start + c.len_utf8();
+                return Err(error(
+                    ParseErrorKind::UnexpectedToken(c.to_string()),
+                    start..end,
+                ));
+            }
+        };
+        let end = chars.peek().map_or(input.len(), |&(end, _)| end);
+        tokens.push((token, start..end));
+    }
+    Ok(tokens)
+}
+
+/// Type of an expression as far as the parser can tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Text,
+    Number,
+    Date,
+    Geo,
+    Any,
+}
+
+impl Type {
+    fn name(self) -> &'static str {
+        match self {
+            Type::Text => "text",
+            Type::Number => "number",
+            Type::Date => "date",
+            Type::Geo => "geometry",
+            Type::Any => "any value",
+        }
+    }
+}
+
+impl From<FieldType> for Type {
+    fn from(field_type: FieldType) -> Self {
+        match field_type {
+            FieldType::Text => Type::Text,
+            FieldType::Integer | FieldType::Number => Type::Number,
+            FieldType::Date | FieldType::DateTime => Type::Date,
+            FieldType::GeoPoint | FieldType::GeoShape => Type::Geo,
+            FieldType::Any => Type::Any,
+        }
+    }
+}
+
+/// Parsed expression with its type and position.
+struct Typed<T: Field> {
+    expression: Expr<T>,
+    kind: Type,
+    span: Range<usize>,
+}
+
+struct Parser<'a, T> {
+    input: &'a str,
+    tokens: Vec<(Token, Range<usize>)>,
+    position: usize,
+    /// Parentheses, `not` and function calls around the current position.
+    depth: usize,
+    phantom: PhantomData<T>,
+}
+
+impl<'a, T: Field> Parser<'a, T> {
+    fn new(input: &'a str) -> Result<Self, ParseError> {
+        Ok(Parser {
+            input,
+            tokens: tokenize(input)?,
+            position: 0,
+            depth: 0,
+            phantom: PhantomData,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<(Token, Range<usize>), ParseError> {
+        let end = self.input.len();
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| error(ParseErrorKind::UnexpectedEnd, end..end))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn unexpected(&self, span: Range<usize>) -> ParseError {
+        let token = self.input[span.clone()].to_string();
+        error(ParseErrorKind::UnexpectedToken(token), span)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<Range<usize>, ParseError> {
+        let (token, span) = self.next()?;
+        if token == expected {
+            Ok(span)
+        } else {
+            Err(self.unexpected(span))
+        }
+    }
+
+    /// Run `parse` one nesting level deeper, failing at `span` past [`MAX_DEPTH`].
+    fn nested<R>(
+        &mut self,
+        span: Range<usize>,
+        parse: impl FnOnce(&mut Self) -> Result<R, ParseError>,
+    ) -> Result<R, ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(error(ParseErrorKind::TooDeep, span));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found =
+            matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Filter<T>, ParseError> {
+        let mut filter = self.and()?;
+        while self.keyword("or") {
+            filter = filter.or(self.and()?);
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter<T>, ParseError> {
+        let mut filter = self.not()?;
+        while self.keyword("and") {
+            filter = filter.and(self.not()?);
+        }
+        Ok(filter)
+    }
+
+    fn not(&mut self) -> Result<Filter<T>, ParseError> {
+        if self.keyword("not") {
+            let span = self.tokens[self.position - 1].1.clone();
+            return Ok(self.nested(span, Self::not)?.not());
+        }
+        self.predicate()
+    }
+
+    fn sort(&mut self) -> Result<Sort<T>, ParseError> {
+        let call = self.tokens.get(self.position + 1).map(|(token, _)| token) == Some(&Token::Open);
+        match self.peek() {
+            Some(Token::Word(name)) if call && name.eq_ignore_ascii_case("random") => {
+                self.position += 2;
+                let (token, span) = self.next()?;
+                let Token::Number(seed) = token else {
+                    return Err(self.unexpected(span));
+                };
+                let seed = seed
+                    .parse()
+                    .map_err(|_| error(ParseErrorKind::InvalidLiteral(seed), span))?;
+                self.expect(Token::Close)?;
+                return Ok(Sort::Random(seed));
+            }
+            Some(Token::Field(name) | Token::Word(name))
+                if !call && T::from_name(name).is_none() =>
+            {
+                let alias = name.clone();
+                self.position += 1;
+                return Ok(Sort::Alias(alias, self.direction()));
+            }
+            _ => {}
+        }
+        let key = self.expression()?.expression;
+        Ok(Sort::By(key, self.direction()))
+    }
+
+    fn direction(&mut self) -> Direction {
+        if self.keyword("desc") {
+            Direction::Descending
+        } else {
+            self.keyword("asc");
+            Direction::Ascending
+        }
+    }
+
+    fn predicate(&mut self) -> Result<Filter<T>, ParseError> {
+        if self.peek() == Some(&Token::Open) {
+            let span = self.next()?.1;
+            return self.nested(span, |parser| {
+                let filter = parser.or()?;
+                parser.expect(Token::Close)?;
+                Ok(filter)
+            });
+        }
+        let call = self.tokens.get(self.position + 1).map(|(token, _)| token) == Some(&Token::Open);
+        if let (true, Some(Token::Word(name))) = (call, self.peek()) {
+            let name = name.to_ascii_lowercase();
+            if let Some(filter) = self.predicate_call(&name)? {
+                return Ok(filter);
+            }
+        }
+        let left = self.expression()?;
+        if self.keyword("is") {
+            let not = self.keyword("not");
+            let (token, span) = self.next()?;
+            if !matches!(token, Token::Word(word) if word.eq_ignore_ascii_case("null")) {
+                return Err(self.unexpected(span));
+            }
+            let filter = Filter::IsNull(left.expression);
+            return Ok(if not { filter.not() } else { filter });
+        }
+        if self.keyword("like") {
+            check(&left, Type::Text)?;
+            let pattern = self.text()?;
+            return Ok(Filter::Like(left.expression, pattern));
+        }
+        let (token, span) = self.next()?;
+        let Token::Operator(comparison) = token else {
+            return Err(self.unexpected(span));
+        };
+        let right = self.expression()?;
+        match left.kind {
+            Type::Geo => Err(mismatch("text, number or date", &left)),
+            Type::Any => Ok(Filter::Compare(
+                left.expression,
+                comparison,
+                right.expression,
+            )),
+            kind => {
+                check(&right, kind)?;
+                Ok(Filter::Compare(
+                    left.expression,
+                    comparison,
+                    right.expression,
+                ))
+            }
+        }
+    }
+
+    /// Boolean function like `startswith`, or `None` if `name` is a scalar function.
+    fn predicate_call(&mut self, name: &str) -> Result<Option<Filter<T>>, ParseError> {
+        if !matches!(
+            name,
+            "startswith" | "search" | "within_distance" | "in_bbox" | "within" | "intersects"
+        ) {
+            return Ok(None);
+        }
+        let name_span = self.next()?.1;
+        self.expect(Token::Open)?;
+        let filter = match name {
+            "startswith" | "search" => {
+                if matches!(self.peek(), Some(Token::Close | Token::Text(_))) {
+                    return Err(error(
+                        ParseErrorKind::Unsupported("search without a field"),
+                        name_span,
+                    ));
+                }
+                let expression = self.expression()?;
+                check(&expression, Type::Text)?;
+                self.expect(Token::Comma)?;
+                let text = self.text()?;
+                if name == "startswith" {
+                    Filter::StartsWith(expression.expression, text)
+                } else {
+                    Filter::Search(expression.expression, text)
+                }
+            }
+            "within_distance" => {
+                let field = self.geo_field()?;
+                self.expect(Token::Comma)?;
+                let (geometry, span) = self.geom()?;
+                let center = point(&geometry)
+                    .ok_or_else(|| error(ParseErrorKind::InvalidLiteral(geometry.clone()), span))?;
+                self.expect(Token::Comma)?;
+                let meters = self.distance()?;
+                Filter::WithinDistance(field, center, meters)
+            }
+            "in_bbox" => {
+                let field = self.geo_field()?;
+                let mut corners = [0.0; 4];
+                for corner in &mut corners {
+                    self.expect(Token::Comma)?;
+                    *corner = self.number()?;
+                }
+                let [lat1, lon1, lat2, lon2] = corners;
+                Filter::InBbox(
+                    field,
+                    GeoPoint2d {
+                        lat: lat1,
+                        lon: lon1,
+                    },
+                    GeoPoint2d {
+                        lat: lat2,
+                        lon: lon2,
+                    },
+                )
+            }
+            "within" => {
+                let field = self.geo_field()?;
+                self.expect(Token::Comma)?;
+                let (geometry, span) = self.geom()?;
+                let ring = wkt(&geometry)
+                    .filter(|(kind, _)| kind == "POLYGON")
+                    .and_then(|(_, rings)| rings.into_iter().next())
+                    .ok_or_else(|| error(ParseErrorKind::InvalidLiteral(geometry.clone()), span))?;
+                let points = ring
+                    .into_iter()
+                    .map(|(lon, lat)| GeoPoint2d { lat, lon })
+                    .collect();
+                Filter::InPolygon(field, points)
+            }
+            _ => {
+                let field = self.geo_field()?;
+                self.expect(Token::Comma)?;
+                let (geometry, span) = self.geom()?;
+                let geometry = geojson(&geometry)
+                    .ok_or_else(|| error(ParseErrorKind::InvalidLiteral(geometry.clone()), span))?;
+                Filter::Intersects(field, Box::new(geometry))
+            }
+        };
+        self.expect(Token::Close)?;
+        Ok(Some(filter))
+    }
+
+    fn expression(&mut self) -> Result<Typed<T>, ParseError> {
+        let (token, span) = self.next()?;
+        let literal = |value: Literal, kind: Type, span: Range<usize>| Typed {
+            expression: Expr::Literal(value),
+            kind,
+            span,
+        };
+        match token {
+            Token::Field(name) => self.field(&name, span),
+            Token::Word(name) if self.peek() == Some(&Token::Open) => {
+                self.nested(span.clone(), |parser| parser.call(&name, span))
+            }
+            Token::Word(name) => self.field(&name, span),
+            Token::Text(text) => Ok(literal(Literal::Text(text), Type::Text, span)),
+            Token::Number(number) => Ok(literal(
+                parse_number(&number, span.clone())?,
+                Type::Number,
+                span,
+            )),
+            Token::Minus => {
+                let (token, number) = self.next()?;
+                let Token::Number(text) = token else {
+                    return Err(self.unexpected(number));
+                };
+                let span = span.start..number.end;
+                let value = parse_number(&format!("-{text}"), span.clone())?;
+                Ok(literal(value, Type::Number, span))
+            }
+            Token::Date(date) => {
+                let value = parse_date(&date)
+                    .ok_or_else(|| error(ParseErrorKind::InvalidLiteral(date), span.clone()))?;
+                Ok(literal(value, Type::Date, span))
+            }
+            _ => Err(self.unexpected(span)),
+        }
+    }
+
+    fn field(&self, name: &str, span: Range<usize>) -> Result<Typed<T>, ParseError> {
+        let field = T::from_name(name)
+            .ok_or_else(|| error(ParseErrorKind::UnknownField(name.to_string()), span.clone()))?;
+        Ok(Typed {
+            kind: field.field_type().into(),
+            expression: Expr::Field(field),
+            span,
+        })
+    }
+
+    fn geo_field(&mut self) -> Result<T, ParseError> {
+        let (token, span) = self.next()?;
+        let field = match token {
+            Token::Field(name) | Token::Word(name) => self.field(&name, span.clone())?,
+            _ => return Err(self.unexpected(span)),
+        };
+        check(&field, Type::Geo)?;
+        match field.expression {
+            Expr::Field(field) => Ok(field),
+            _ => unreachable!("field() always returns a field"),
+        }
+    }
+
+    fn call(&mut self, name: &str, name_span: Range<usize>) -> Result<Typed<T>, ParseError> {
+        let (function, argument, result) = match name.to_ascii_lowercase().as_str() {
+            "now" => return self.now(name_span),
+            "distance" => return self.distance_call(name_span),
+            "year" => (Function::Year, Type::Date, Type::Number),
+            "month" => (Function::Month, Type::Date, Type::Number),
+            "day" => (Function::Day, Type::Date, Type::Number),
+            "hour" => (Function::Hour, Type::Date, Type::Number),
+            "minute" => (Function::Minute, Type::Date, Type::Number),
+            "second" => (Function::Second, Type::Date, Type::Number),
+            "date_format" => (Function::DateFormat, Type::Date, Type::Text),
+            "lower" => (Function::Lower, Type::Text, Type::Text),
+            "upper" => (Function::Upper, Type::Text, Type::Text),
+            "length" => (Function::Length, Type::Text, Type::Number),
+            _ => {
+                return Err(error(
+                    ParseErrorKind::UnknownFunction(name.to_string()),
+                    name_span,
+                ))
+            }
+        };
+        self.expect(Token::Open)?;
+        let first = self.expression()?;
+        check(&first, argument)?;
+        let mut arguments = vec![first.expression];
+        if function == Function::DateFormat {
+            self.expect(Token::Comma)?;
+            arguments.push(Expr::Literal(Literal::Text(self.text()?)));
+        }
+        let end = self.expect(Token::Close)?.end;
+        Ok(Typed {
+            expression: Expr::Call(function, arguments),
+            kind: result,
+            span: name_span.start..end,
+        })
+    }
+
+    /// `distance(field, geom'POINT(lon lat)')`
+    fn distance_call(&mut self, name_span: Range<usize>) -> Result<Typed<T>, ParseError> {
+        self.expect(Token::Open)?;
+        let field = self.geo_field()?;
+        self.expect(Token::Comma)?;
+        let (geometry, span) = self.geom()?;
+        let point = point(&geometry)
+            .ok_or_else(|| error(ParseErrorKind::InvalidLiteral(geometry.clone()), span))?;
+        let end = self.expect(Token::Close)?.end;
+        Ok(Typed {
+            expression: Expr::Distance(field, point),
+            kind: Type::Number,
+            span: name_span.start..end,
+        })
+    }
+
+    /// `now(days=-7, hours=2)`
+    fn now(&mut self, name_span: Range<usize>) -> Result<Typed<T>, ParseError> {
+        self.expect(Token::Open)?;
+        let mut shift = Shift::default();
+        while self.peek() != Some(&Token::Close) {
+            let (token, span) = self.next()?;
+            let Token::Word(unit) = token else {
+                return Err(self.unexpected(span));
+            };
+            let slot = match unit.to_ascii_lowercase().as_str() {
+                "years" => &mut shift.years,
+                "months" => &mut shift.months,
+                "weeks" => &mut shift.weeks,
+                "days" => &mut shift.days,
+                "hours" => &mut shift.hours,
+                "minutes" => &mut shift.minutes,
+                "seconds" => &mut shift.seconds,
+                _ => return Err(self.unexpected(span)),
+            };
+            let (token, span) = self.next()?;
+            if token != Token::Operator(Comparison::Equal) {
+                return Err(self.unexpected(span));
+            }
+            *slot = self.integer()?;
+            if self.peek() != Some(&Token::Comma) {
+                break;
+            }
+            self.position += 1;
+        }
+        let end = self.expect(Token::Close)?.end;
+        Ok(Typed {
+            expression: Expr::Now(shift),
+            kind: Type::Date,
+            span: name_span.start..end,
+        })
+    }
+
+    fn integer(&mut self) -> Result<i64, ParseError> {
+        let negative = self.peek() == Some(&Token::Minus);
+        if negative {
+            self.position += 1;
+        }
+        let (token, span) = self.next()?;
+        let Token::Number(number) = token else {
+            return Err(self.unexpected(span));
+        };
+        let number = if negative {
+            format!("-{number}")
+        } else {
+            number
+        };
+        number
+            .parse()
+            .map_err(|_| error(ParseErrorKind::InvalidLiteral(number), span))
+    }
+
+    fn text(&mut self) -> Result<String, ParseError> {
+        match self.next()? {
+            (Token::Text(text), _) => Ok(text),
+            (_, span) => Err(self.unexpected(span)),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, ParseError> {
+        let negative = self.peek() == Some(&Token::Minus);
+        if negative {
+            self.position += 1;
+        }
+        let (token, span) = self.next()?;
+        let Token::Number(number) = token else {
+            return Err(self.unexpected(span));
+        };
+        let value: f64 = number
+            .parse()
+            .map_err(|_| error(ParseErrorKind::InvalidLiteral(number), span))?;
+        Ok(if negative { -value } else { value })
+    }
+
+    fn geom(&mut self) -> Result<(String, Range<usize>), ParseError> {
+        match self.next()? {
+            (Token::Geom(geometry), span) => Ok((geometry, span)),
+            (_, span) => Err(self.unexpected(span)),
+        }
+    }
+
+    /// Distance such as `100m` or `2.5km`, in meters.
+    fn distance(&mut self) -> Result<f64, ParseError> {
+        let (token, span) = self.next()?;
+        let text = match token {
+            Token::Word(text) | Token::Number(text) => text,
+            _ => return Err(self.unexpected(span)),
+        };
+        let split = text
+            .rfind(|c: char| c.is_ascii_digit() || c == '.')
+            .map_or(0, |last| last + 1);
+        let (number, unit) = text.split_at(split);
+        let factor = match unit {
+            "" | "m" => 1.0,
+            "km" => 1000.0,
+            "mi" => 1609.344,
+            "yd" => 0.9144,
+            "ft" => 0.3048,
+            _ => return Err(error(ParseErrorKind::InvalidLiteral(text), span)),
+        };
+        match number.parse::<f64>() {
+            Ok(number) => Ok(number * factor),
+            Err(_) => Err(error(ParseErrorKind::InvalidLiteral(text), span)),
+        }
+    }
+}
+
+fn mismatch<T: Field>(expected: &'static str, found: &Typed<T>) -> ParseError {
+    error(
+        ParseErrorKind::TypeMismatch {
+            expected,
+            found: found.kind.name(),
+        },
+        found.span.clone(),
+    )
+}
+
+/// `typed` can be used where a value of type `expected` is needed.
+///
+/// Text literals are accepted as dates if they can be read as one.
+fn check<T: Field>(typed: &Typed<T>, expected: Type) -> Result<(), ParseError> {
+    let date_text = match &typed.expression {
+        Expr::Literal(Literal::Text(text)) => Scalar::Text(text.clone()).datetime().is_some(),
+        _ => false,
+    };
+    match (expected, typed.kind) {
+        (Type::Any, _) | (_, Type::Any) => Ok(()),
+        (expected, found) if expected == found => Ok(()),
+        (Type::Date, Type::Text) if date_text => Ok(()),
+        (expected, _) => Err(mismatch(expected.name(), typed)),
+    }
+}
+
+/// Integers outside of `i64` are rejected, as a float could not print them back unchanged.
+fn parse_number(number: &str, span: Range<usize>) -> Result<Literal, ParseError> {
+    let integer = number
+        .trim_start_matches('-')
+        .bytes()
+        .all(|b| b.is_ascii_digit());
+    if integer {
+        return number
+            .parse::<i64>()
+            .map(Literal::Integer)
+            .map_err(|_| error(ParseErrorKind::InvalidLiteral(number.to_string()), span));
+    }
+    number
+        .parse::<f64>()
+        .map(Literal::Number)
+        .map_err(|_| error(ParseErrorKind::InvalidLiteral(number.to_string()), span))
+}
+
+fn parse_date(date: &str) -> Option<Literal> {
+    if let Ok(date) = Date::parse(date, format_description!("[year]-[month]-[day]")) {
+        return Some(Literal::Date(date));
+    }
+    OffsetDateTime::parse(date, &Rfc3339)
+        .ok()
+        .map(Literal::DateTime)
+}
+
+/// Ring of longitude and latitude pairs.
+type Ring = Vec<(f64, f64)>;
+
+/// Kind and rings of a WKT `POINT` or `POLYGON`.
+fn wkt(geometry: &str) -> Option<(String, Vec<Ring>)> {
+    let open = geometry.find('(')?;
+    let kind = geometry[..open].trim().to_ascii_uppercase();
+    let body = geometry[open..].trim();
+    let rings = body
+        .split(')')
+        .map(|ring| ring.trim_start_matches(|c: char| c == ',' || c == '(' || c.is_whitespace()))
+        .filter(|ring| !ring.is_empty())
+        .map(|ring| {
+            ring.split(',')
+                .map(|point| {
+                    let mut coordinates = point.split_whitespace().map(str::parse::<f64>);
+                    match (coordinates.next(), coordinates.next(), coordinates.next()) {
+                        (Some(Ok(lon)), Some(Ok(lat)), None) => Some((lon, lat)),
+                        _ => None,
+                    }
+                })
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<Vec<_>>>()?;
+    match kind.as_str() {
+        "POINT" if rings.len() == 1 && rings[0].len() == 1 => Some((kind, rings)),
+        "POLYGON" if !rings.is_empty() => Some((kind, rings)),
+        _ => None,
+    }
+}
+
+/// Geometry from GeoJSON, or from a WKT `POINT` or `POLYGON`.
+fn geojson(geometry: &str) -> Option<GeoJson> {
+    if let Ok(geojson) = geometry.parse::<GeoJson>() {
+        return Some(geojson);
+    }
+    let (kind, rings) = wkt(geometry)?;
+    let rings: Vec<Vec<Vec<f64>>> = rings
+        .into_iter()
+        .map(|ring| ring.into_iter().map(|(lon, lat)| vec![lon, lat]).collect())
+        .collect();
+    let value = match kind.as_str() {
+        "POINT" => geojson::Value::Point(rings[0][0].clone()),
+        _ => geojson::Value::Polygon(rings),
+    };
+    Some(GeoJson::Geometry(Geometry::new(value)))
+}
+
+/// Point from a WKT `POINT` or a GeoJSON point.
+fn point(geometry: &str) -> Option<GeoPoint2d> {
+    match geojson(geometry)? {
+        GeoJson::Geometry(Geometry {
+            value: geojson::Value::Point(point),
+            ..
+        }) if point.len() >= 2 => Some(GeoPoint2d {
+            lat: point[1],
+            lon: point[0],
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,59 @@
+use crate::client::Portal;
+use crate::dataset::Dataset;
+
+/// Runtime description of a generated dataset module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Descriptor {
+    pub portal: Portal,
+    pub id: &'static str,
+    /// Cargo feature enabling the module, e.g. `bs100003`
+    pub feature: &'static str,
+    /// Module path below the crate root, e.g. `stadt::basler_index_der_konsumentenpreise`
+    pub module: &'static str,
+    pub title: &'static str,
+    /// Portal names of the variants of the module's `Field` enum.
+    pub fields: &'static [&'static str],
+    pub(crate) enabled: bool,
+}
+
+impl Descriptor {
+    /// Module name without the `stadt::` or `land::` prefix.
+    pub fn module_name(&self) -> &'static str {
+        self.module
+            .rsplit_once("::")
+            .map_or(self.module, |(_, name)| name)
+    }
+
+    /// Descriptor of a compiled-in dataset by its portal and id.
+    pub fn by_id(portal: Portal, id: &str) -> Option<&'static Descriptor> {
+        registry()
+            .into_iter()
+            .find(|descriptor| descriptor.portal == portal && descriptor.id == id)
+    }
+
+    /// Descriptor of a compiled-in dataset by its module name, with or without the
+    /// `stadt::` or `land::` prefix.
+    pub fn by_module(module: &str) -> Option<&'static Descriptor> {
+        registry()
+            .into_iter()
+            .find(|descriptor| descriptor.module == module || descriptor.module_name() == module)
+    }
+
+    pub fn of<D: Dataset>() -> &'static Descriptor {
+        generated()
+            .find(|descriptor| descriptor.portal == D::PORTAL && descriptor.id == D::ID)
+            .expect("every generated module has a descriptor")
+    }
+}
+
+/// Every dataset this crate was generated for, whether or not its feature is enabled.
+pub(crate) fn generated() -> impl Iterator<Item = &'static Descriptor> {
+    crate::stadt::DATASETS.iter().chain(crate::land::DATASETS)
+}
+
+/// Every dataset whose feature is enabled in this build.
+pub fn registry() -> Vec<&'static Descriptor> {
+    generated()
+        .filter(|descriptor| descriptor.enabled)
+        .collect()
+}
@@ -0,0 +1,181 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// When and how often failed requests are sent again.
+///
+/// Only GET requests are sent by the client, so every request can safely be repeated.
+/// Responses with 429 Too Many Requests, 500, 502, 503 or 504 and connection errors or
+/// timeouts are retried, waiting an exponentially growing, randomized delay in between.
+/// A `Retry-After` header replaces the delay; if it asks to wait longer than `max_delay`,
+/// the error is returned right away instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt, `0` disables retrying.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for every further one.
+    pub base_delay: Duration,
+    /// Upper bound for a single delay.
+    pub max_delay: Duration,
+    /// Wait a random duration between zero and the delay, so concurrent jobs don't retry in lockstep.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    /// Three retries, starting at half a second and waiting at most 30 seconds, with jitter.
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry, which is what a new `Client` does.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Delay before retry number `retry`, counting from zero.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        if self.jitter {
+            delay.mul_f64(fastrand::f64())
+        } else {
+            delay
+        }
+    }
+
+    /// Delay before retry number `retry` after a response, or `None` if it is not retried.
+    pub(crate) fn after_status(
+        &self,
+        retry: u32,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Option<Duration> {
+        let transient = matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        );
+        if !transient || retry >= self.max_retries {
+            return None;
+        }
+        match retry_after(headers) {
+            Some(delay) if delay > self.max_delay => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff(retry)),
+        }
+    }
+
+    /// Delay before retry number `retry` after a failed request, or `None` if it is not retried.
+    pub(crate) fn after_error(&self, retry: u32, error: &reqwest::Error) -> Option<Duration> {
+        let transient = error.is_timeout() || error.is_connect();
+        (transient && retry < self.max_retries).then(|| self.backoff(retry))
+    }
+}
+
+/// `Retry-After` in seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Token bucket limiting how many requests are sent per time interval.
+///
+/// Clones share the same bucket, so one limiter can be given to several clients to cap the
+/// requests of all of them together. Requests over the limit wait for their turn in order.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    /// Tokens added per second.
+    rate: f64,
+    /// Negative while requests are waiting for tokens that are not refilled yet.
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    /// Allow `requests` requests per `interval`, all of which may be sent at once.
+    pub fn new(requests: u32, interval: Duration) -> Self {
+        Self::with_burst(requests, interval, requests)
+    }
+
+    /// Allow `requests` requests per `interval`, at most `burst` of them at once.
+    pub fn with_burst(requests: u32, interval: Duration, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        RateLimiter {
+            bucket: Arc::new(Mutex::new(Bucket {
+                capacity,
+                rate: f64::from(requests.max(1)) / interval.as_secs_f64().max(f64::EPSILON),
+                tokens: capacity,
+                refilled: Instant::now(),
+            })),
+        }
+    }
+
+    /// Take a token, waiting until one is available.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self
+                .bucket
+                .lock()
+                .unwrap_or_else(|error| error.into_inner());
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * bucket.rate).min(bucket.capacity);
+            bucket.refilled = now;
+            bucket.tokens -= 1.0;
+            if bucket.tokens >= 0.0 {
+                return;
+            }
+            Duration::from_secs_f64(-bucket.tokens / bucket.rate)
+        };
+        tokio::time::sleep(wait).await;
+    }
+}
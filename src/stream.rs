@@ -0,0 +1,103 @@
+#![allow(dead_code)]
+use crate::client::{Client, Portal, Query};
+use crate::common::{Field, Order};
+use crate::error::Error;
+use crate::filter::Filter;
+use futures_util::stream::{self, Stream};
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+
+/// Highest `offset + limit` the records endpoint accepts.
+pub const MAX_OFFSET: u64 = 10_000;
+
+const PAGE_SIZE: u64 = 100;
+
+struct Pages<R> {
+    client: Client,
+    portal: Portal,
+    dataset: String,
+    query: Query,
+    offset: u64,
+    total_count: Option<u64>,
+    buffer: VecDeque<R>,
+    done: bool,
+}
+
+impl<R: DeserializeOwned> Pages<R> {
+    async fn next(mut self) -> Option<(Result<R, Error>, Self)> {
+        loop {
+            if let Some(record) = self.buffer.pop_front() {
+                return Some((Ok(record), self));
+            }
+            if self.done || self.total_count.is_some_and(|total| self.offset >= total) {
+                return None;
+            }
+            // Only reached if records were added while paging, as the first page already
+            // fails for more than `MAX_OFFSET` records. Requesting further would send
+            // `limit=0` and end the stream silently.
+            if self.offset >= MAX_OFFSET {
+                self.done = true;
+                let total_count = self.total_count.unwrap_or(MAX_OFFSET);
+                return Some((Err(Error::OffsetLimit { total_count }), self));
+            }
+            let limit = PAGE_SIZE.min(MAX_OFFSET - self.offset) as u8;
+            let page = self
+                .client
+                .records_page(self.portal, &self.dataset, limit, self.offset, &self.query)
+                .await;
+            match page {
+                // Fail on the first page rather than after paging up to the ceiling.
+                Ok(data) if self.offset == 0 && data.total_count > MAX_OFFSET => {
+                    self.done = true;
+                    let total_count = data.total_count;
+                    return Some((Err(Error::OffsetLimit { total_count }), self));
+                }
+                Ok(data) => {
+                    self.total_count = Some(data.total_count);
+                    self.offset += data.results.len() as u64;
+                    self.done = data.results.is_empty();
+                    self.buffer.extend(data.results);
+                }
+                Err(error) => {
+                    self.done = true;
+                    return Some((Err(error), self));
+                }
+            }
+        }
+    }
+}
+
+impl Client {
+    /// Lazily page through all records matching `filter`.
+    ///
+    /// Pages of 100 records are requested as the stream is polled. The records endpoint
+    /// cannot go beyond [`MAX_OFFSET`], so for larger result sets the first item is
+    /// [`Error::OffsetLimit`], after a single request, instead of a stream cut off silently.
+    /// This holds even for consumers that only `take` a few records; they get the error and
+    /// no records, and should use `get` or narrow the filter instead.
+    ///
+    /// The stream is not `Unpin`, pin it with `std::pin::pin!` before calling `next` on it.
+    pub(crate) fn records_stream<R, F>(
+        &self,
+        portal: Portal,
+        dataset: &str,
+        order: Order<F>,
+        filter: Option<Filter<F>>,
+    ) -> impl Stream<Item = Result<R, Error>> + Send + 'static
+    where
+        R: DeserializeOwned + Send + 'static,
+        F: Field,
+    {
+        let pages = Pages {
+            client: self.clone(),
+            portal,
+            dataset: dataset.to_string(),
+            query: Query::new(order, filter),
+            offset: 0,
+            total_count: None,
+            buffer: VecDeque::new(),
+            done: false,
+        };
+        stream::unfold(pages, Pages::next)
+    }
+}
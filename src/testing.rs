@@ -0,0 +1,186 @@
+//! Record portal responses to fixture files and replay them, so tests run without network access.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), basel::Error> {
+//! use basel::dynamic::Dataset;
+//! use basel::testing::Fixtures;
+//! use basel::{Client, Order, Portal};
+//!
+//! let client = Client::new().fixtures(Fixtures::new("tests/fixtures"));
+//! let strassennamen = Dataset::new(Portal::Stadt, "100189").client(&client);
+//! let first_page = strassennamen.get(10, 0, Order::new(), None).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Fixtures are stored as `<dir>/<stadt|land>/<dataset>/<endpoint>-<hash>.fixture`, where the
+//! hash covers the endpoint and its sorted query parameters, including `limit` and `offset`.
+//! Each file holds one line of JSON describing the request, followed by the response body.
+pub use crate::mock::MockServer;
+
+use crate::cache::hash;
+use crate::client::{Client, Portal};
+use crate::error::Error;
+use bytes::Bytes;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Whether requests are answered by fixtures, the portals or both.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FixtureMode {
+    /// Replay existing fixtures and record the missing ones.
+    #[default]
+    Auto,
+    /// Send every request to the portal and overwrite its fixture.
+    Record,
+    /// Never send a request, a missing fixture is an error.
+    Replay,
+}
+
+impl FixtureMode {
+    /// Mode from the `BASEL_FIXTURES` environment variable, `auto`, `record` or `replay`.
+    ///
+    /// Unset or unknown values are `Auto`, so CI can use `replay` to fail on missing fixtures.
+    pub fn from_env() -> Self {
+        match std::env::var("BASEL_FIXTURES").as_deref() {
+            Ok("record") => FixtureMode::Record,
+            Ok("replay") => FixtureMode::Replay,
+            _ => FixtureMode::Auto,
+        }
+    }
+}
+
+/// Directory of fixtures and how to use it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixtures {
+    dir: PathBuf,
+    mode: FixtureMode,
+}
+
+/// First line of a fixture file, followed by the body.
+#[derive(Serialize, Deserialize)]
+struct Header {
+    request: String,
+    status: u16,
+    content_type: Option<String>,
+}
+
+impl Fixtures {
+    /// Fixtures in `dir`, in the mode given by [`FixtureMode::from_env`].
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Fixtures {
+            dir: dir.as_ref().to_path_buf(),
+            mode: FixtureMode::from_env(),
+        }
+    }
+
+    pub fn mode(mut self, mode: FixtureMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Request as matched against fixtures and the file its fixture is stored in.
+    fn locate(&self, portal: Portal, url: &reqwest::Url) -> (String, PathBuf) {
+        let path = url.path();
+        let path = path.split_once("/catalog/").map_or(path, |(_, path)| path);
+        let mut query: Vec<_> = url.query_pairs().collect();
+        query.sort();
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(query)
+            .finish();
+        let request = format!("{path}?{query}");
+
+        let segments: Vec<_> = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let (dataset, endpoint) = match segments.as_slice() {
+            ["datasets", dataset] => (*dataset, "metadata".to_string()),
+            ["datasets", dataset, endpoint @ ..] => (*dataset, endpoint.join("-")),
+            endpoint => ("catalog", endpoint.join("-")),
+        };
+        let portal = match portal {
+            Portal::Stadt => "stadt",
+            Portal::Land => "land",
+        };
+        let file = format!("{endpoint}-{:016x}.fixture", hash(&request));
+        (request, self.dir.join(portal).join(dataset).join(file))
+    }
+}
+
+impl Client {
+    /// Answer requests from `fixtures`, recording the ones sent to a portal.
+    ///
+    /// Only successful responses are recorded. The rate limiter and retry policy apply to
+    /// requests sent while recording; cached entries are revalidated against the fixtures.
+    pub fn fixtures(mut self, fixtures: Fixtures) -> Self {
+        self.fixtures = Some(fixtures);
+        self
+    }
+
+    pub(crate) async fn send_fixture(
+        &self,
+        fixtures: &Fixtures,
+        portal: Portal,
+        url: reqwest::Url,
+    ) -> Result<reqwest::Response, Error> {
+        let (request, path) = fixtures.locate(portal, &url);
+        let error = |source| Error::Fixture {
+            path: path.clone(),
+            source,
+        };
+
+        let replay = match fixtures.mode {
+            FixtureMode::Auto => path.exists(),
+            FixtureMode::Record => false,
+            FixtureMode::Replay => true,
+        };
+        let (header, body) = if replay {
+            let file = std::fs::read(&path).map_err(error)?;
+            let newline = file.iter().position(|&byte| byte == b'\n');
+            let header = newline.and_then(|newline| serde_json::from_slice(&file[..newline]).ok());
+            let (Some(newline), Some(header)) = (newline, header) else {
+                let invalid = io::Error::new(io::ErrorKind::InvalidData, "not a fixture file");
+                return Err(error(invalid));
+            };
+            (header, Bytes::copy_from_slice(&file[newline + 1..]))
+        } else {
+            let response = self.send_live(url, HeaderMap::new()).await?;
+            let header = Header {
+                request,
+                status: response.status().as_u16(),
+                content_type: response
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string),
+            };
+            let body = response.bytes().await?;
+            let mut file = serde_json::to_vec(&header)
+                .map_err(io::Error::from)
+                .map_err(error)?;
+            file.push(b'\n');
+            file.extend_from_slice(&body);
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(error)?;
+            }
+            std::fs::write(&path, file).map_err(error)?;
+            (header, body)
+        };
+
+        let mut response = http::Response::builder().status(header.status);
+        if let Some(content_type) = header.content_type {
+            response = response.header(CONTENT_TYPE, content_type);
+        }
+        let response = response
+            .body(body)
+            .map_err(|source| error(io::Error::new(io::ErrorKind::InvalidData, source)))?;
+        Ok(response.into())
+    }
+}
@@ -0,0 +1,92 @@
+use basel::dynamic::Field;
+use basel::{Aggregation, Client, Error, Expr, Order};
+use common::{dataset, serve};
+
+mod common;
+
+#[tokio::test]
+async fn groups_beyond_the_limit_are_an_error() {
+    let (url, _) = serve(&[
+        "200 OK\r\nContent-Type: application/json\r\n\r\n{\"total_count\": 20001, \"results\": [{\"jahr\": 2024, \"n\": 3}]}",
+    ]);
+    let aggregation = Aggregation::new().group_by(Field::new("jahr")).count("n");
+    let error = dataset(&url, Client::new())
+        .aggregate(aggregation, Order::new(), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::TooManyGroups { total_count: 20001 }));
+}
+
+#[tokio::test]
+async fn non_finite_percentile_is_rejected() {
+    let (url, requests) = serve(&[common::OK]);
+    let aggregation = Aggregation::new().percentile(Field::new("wert"), f64::NAN, "p");
+    let error = dataset(&url, Client::new())
+        .aggregate(aggregation, Order::new(), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::InvalidValue(ref value) if value == "NaN"));
+    assert_eq!(requests.count(), 0);
+}
+
+#[tokio::test]
+async fn out_of_range_percentile_is_rejected() {
+    let (url, requests) = serve(&[common::OK]);
+    let aggregation = Aggregation::new().percentile(Field::new("wert"), 101.0, "p");
+    let error = dataset(&url, Client::new())
+        .aggregate(aggregation, Order::new(), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::InvalidValue(ref value) if value == "101"));
+    assert_eq!(requests.count(), 0);
+}
+
+#[tokio::test]
+async fn ungrouped_aggregation_keeps_the_default_limit() {
+    let (url, requests) = serve(&[
+        "200 OK\r\nContent-Type: application/json\r\n\r\n{\"total_count\": 1, \"results\": [{\"n\": 3}]}",
+    ]);
+    let aggregation = Aggregation::new().count("n");
+    let groups = dataset(&url, Client::new())
+        .aggregate(aggregation, Order::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(groups.len(), 1);
+    let request = requests.last();
+    assert!(
+        request.contains("select=count%28*%29+as+%60n%60"),
+        "{request}"
+    );
+    assert!(!request.contains("limit="), "{request}");
+}
+
+#[tokio::test]
+async fn grouped_aggregation_asks_for_every_group() {
+    let (url, requests) = serve(&[common::OK]);
+    let aggregation = Aggregation::new().group_by(Field::new("jahr")).count("n");
+    dataset(&url, Client::new())
+        .aggregate(aggregation, Order::new(), None)
+        .await
+        .unwrap();
+    assert!(requests.last().contains("limit=20000"));
+}
+
+#[tokio::test]
+async fn grouping_by_expressions() {
+    let (url, requests) = serve(&[
+        "200 OK\r\nContent-Type: application/json\r\n\r\n{\"results\": [{\"ort\": \"basel\", \"monat\": 3, \"n\": 2}]}",
+    ]);
+    let aggregation = Aggregation::new()
+        .group_by_expr(Expr::field(Field::new("station")).lower(), "ort")
+        .group_by_expr(Expr::field(Field::new("datum")).month(), "monat")
+        .count("n");
+    let groups = dataset(&url, Client::new())
+        .aggregate(aggregation, Order::new(), None)
+        .await
+        .unwrap();
+    assert!(requests.last().contains(
+        "&group_by=lower%28%60station%60%29+as+%60ort%60%2c+month%28%60datum%60%29+as+%60monat%60&"
+    ));
+    assert_eq!(groups[0].get::<String>("ort").as_deref(), Some("basel"));
+    assert_eq!(groups[0].get::<u32>("monat"), Some(3));
+}
@@ -0,0 +1,82 @@
+use basel::blocking::Client;
+use basel::stadt::belegung_der_elektroauto_ladestationen_der_iwb::{blocking, fields, Field};
+use basel::testing::MockServer;
+use basel::{ExportFormat, Order};
+use serde_json::json;
+
+fn server() -> MockServer {
+    let server = MockServer::start().unwrap();
+    server.dataset(
+        "100004",
+        (1..=150).map(|parkingfield| {
+            let status = if parkingfield % 3 == 0 {
+                "besetzt"
+            } else {
+                "frei"
+            };
+            json!({ "parkingfield": parkingfield, "totalparkings": 150, "status": status })
+        }),
+    );
+    server
+}
+
+#[test]
+fn get() {
+    let server = server();
+    let client = Client::from(server.client());
+    let data = blocking::get_with(
+        &client,
+        10,
+        0,
+        Order::new().descending(Field::Parkingfield),
+        Some(fields::STATUS.equal("besetzt")),
+    )
+    .unwrap();
+    assert_eq!(data.total_count, 50);
+    assert_eq!(data.results[0].parkingfield, Some(150));
+    assert_eq!(data.results.len(), 10);
+}
+
+#[test]
+fn select() {
+    let server = server();
+    let client = Client::from(server.client());
+    let data = blocking::select_with(&client, &[Field::Status], 1, 0, Order::new(), None).unwrap();
+    assert_eq!(data.results[0].status.as_deref(), Some("frei"));
+    assert_eq!(data.results[0].parkingfield, None);
+}
+
+#[test]
+fn pagination_and_exports() {
+    let server = server();
+    let client = Client::from(server.client());
+
+    let all: Vec<_> = blocking::stream_with(&client, Order::new(), None)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(all.len(), 150);
+    assert_eq!(server.requests(), 2);
+
+    let exported = blocking::export_with(&client, Order::new(), None)
+        .map(|record| record.unwrap().parkingfield.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(exported, (1..=150).collect::<Vec<_>>());
+
+    let csv: Vec<u8> = blocking::export_bytes_with(&client, ExportFormat::Csv, Order::new(), None)
+        .flat_map(|chunk| chunk.unwrap().to_vec())
+        .collect();
+    assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 151);
+}
+
+#[test]
+fn errors() {
+    let server = server();
+    let client = Client::from(server.client());
+    server.fail(500, 1);
+    let error = blocking::get_with(&client, 10, 0, Order::new(), None).unwrap_err();
+    assert_eq!(
+        error.status(),
+        Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+    );
+    assert!(blocking::get_with(&client, 10, 0, Order::new(), None).is_ok());
+}
@@ -0,0 +1,167 @@
+use basel::dynamic::Dataset;
+use basel::{
+    Cache, CacheEntry, CacheStats, CacheStatus, Client, DiskCache, Error, HttpCache, MemoryCache,
+    Order,
+};
+use common::{dataset, serve};
+use std::time::{Duration, SystemTime};
+
+mod common;
+
+const TAGGED: &str = "200 OK\r\nETag: \"v1\"\r\nLast-Modified: Wed, 01 Jan 2025 00:00:00 GMT\r\n\r\n{\"total_count\": 1, \"results\": [{\"id\": 1}]}";
+const NOT_MODIFIED: &str = "304 Not Modified\r\n\r\n";
+const CHANGED: &str =
+    "200 OK\r\nETag: \"v2\"\r\n\r\n{\"total_count\": 2, \"results\": [{\"id\": 1}, {\"id\": 2}]}";
+
+async fn total_count(dataset: &Dataset) -> u64 {
+    dataset
+        .get(10, 0, Order::new(), None)
+        .await
+        .unwrap()
+        .total_count
+}
+
+#[tokio::test]
+async fn revalidates_with_validators() {
+    let (url, requests) = serve(&[TAGGED, NOT_MODIFIED, CHANGED, NOT_MODIFIED]);
+    let cache = HttpCache::new(MemoryCache::new());
+    let dataset = dataset(&url, Client::new().cache(cache.clone()));
+
+    assert_eq!(total_count(&dataset).await, 1);
+    assert!(!requests.last().contains("if-none-match"));
+    assert_eq!(total_count(&dataset).await, 1);
+    assert!(requests.last().contains("if-none-match: \"v1\""));
+    assert!(requests
+        .last()
+        .contains("if-modified-since: wed, 01 jan 2025 00:00:00 gmt"));
+    assert_eq!(total_count(&dataset).await, 2);
+    assert_eq!(total_count(&dataset).await, 2);
+    assert!(requests.last().contains("if-none-match: \"v2\""));
+
+    assert_eq!(requests.count(), 4);
+    let stats = CacheStats {
+        fresh: 0,
+        revalidated: 2,
+        misses: 2,
+    };
+    assert_eq!(cache.stats(), stats);
+    assert_eq!(cache.dataset_stats("100089"), stats);
+    assert_eq!(cache.dataset_stats("100040"), CacheStats::default());
+}
+
+#[tokio::test]
+async fn fresh_entries_skip_the_request() {
+    let (url, requests) = serve(&[TAGGED, CHANGED]);
+    let cache = HttpCache::new(MemoryCache::new())
+        .ttl(Duration::ZERO)
+        .dataset_ttl("100089", Duration::from_secs(3600));
+    let dataset = dataset(&url, Client::new().cache(cache.clone()));
+
+    assert_eq!(total_count(&dataset).await, 1);
+    assert_eq!(total_count(&dataset).await, 1);
+    assert_eq!(requests.count(), 1);
+    assert_eq!(cache.stats().hits(), 1);
+    assert_eq!(cache.stats().fresh, 1);
+
+    // Different parameters are a different entry.
+    dataset.get(5, 0, Order::new(), None).await.unwrap();
+    assert_eq!(requests.count(), 2);
+}
+
+#[tokio::test]
+async fn pages_tell_their_cache_status() {
+    let (url, _) = serve(&[TAGGED, NOT_MODIFIED, TAGGED]);
+    let cache = HttpCache::new(MemoryCache::new());
+    let cached = dataset(&url, Client::new().cache(cache));
+    let get = || cached.get(10, 0, Order::new(), None);
+
+    assert_eq!(get().await.unwrap().cache, Some(CacheStatus::Miss));
+    assert_eq!(get().await.unwrap().cache, Some(CacheStatus::Revalidated));
+
+    let uncached = dataset(&url, Client::new());
+    let data = uncached.get(10, 0, Order::new(), None).await.unwrap();
+    assert_eq!(data.cache, None);
+}
+
+#[tokio::test]
+async fn disk_cache_survives_restarts() {
+    let dir = std::env::temp_dir().join(format!("basel-cache-{}", std::process::id()));
+    let (url, requests) = serve(&[TAGGED, NOT_MODIFIED]);
+
+    let first = HttpCache::new(DiskCache::new(&dir).unwrap());
+    assert_eq!(
+        total_count(&dataset(&url, Client::new().cache(first.clone()))).await,
+        1
+    );
+    let second = HttpCache::new(DiskCache::new(&dir).unwrap());
+    assert_eq!(
+        total_count(&dataset(&url, Client::new().cache(second.clone()))).await,
+        1
+    );
+
+    assert!(requests.last().contains("if-none-match: \"v1\""));
+    assert_eq!(second.stats().revalidated, 1);
+    let key = format!("{url}/api/explore/v2.1/catalog/datasets/100089/records?limit=10&offset=0");
+    let entry = second.store().get(&key).unwrap();
+    assert_eq!(entry.etag.as_deref(), Some("\"v1\""));
+    second.store().remove(&key);
+    assert!(second.store().get(&key).is_none());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn entries_without_validators_or_ttl_are_not_kept() {
+    const UNTAGGED: &str = "200 OK\r\n\r\n{\"total_count\": 1, \"results\": [{\"id\": 1}]}";
+    let (url, _) = serve(&[TAGGED, UNTAGGED, UNTAGGED]);
+    let key = format!("{url}/api/explore/v2.1/catalog/datasets/100089/records?limit=10&offset=0");
+
+    let cache = HttpCache::new(MemoryCache::new());
+    let cached = dataset(&url, Client::new().cache(cache.clone()));
+    total_count(&cached).await;
+    assert!(cache.store().get(&key).is_some());
+    // The stale entry is dropped as well once the response loses its validators.
+    total_count(&cached).await;
+    assert!(cache.store().get(&key).is_none());
+
+    let cache = HttpCache::new(MemoryCache::new()).ttl(Duration::from_secs(60));
+    total_count(&dataset(&url, Client::new().cache(cache.clone()))).await;
+    assert!(cache.store().get(&key).is_some());
+}
+
+#[test]
+fn memory_cache_evicts_the_least_recently_used() {
+    let entry = |body: &'static str| CacheEntry {
+        body: body.into(),
+        etag: None,
+        last_modified: None,
+        stored: SystemTime::now(),
+    };
+    let cache = MemoryCache::new().capacity(2);
+    cache.put("a", entry("a"));
+    cache.put("b", entry("b"));
+    assert!(cache.get("a").is_some());
+    cache.put("c", entry("c"));
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get("b").is_none());
+    assert_eq!(cache.get("a").unwrap().body, "a");
+
+    cache.put("a", entry("new"));
+    cache.put("d", entry("d"));
+    assert!(cache.get("c").is_none());
+    assert_eq!(cache.get("a").unwrap().body, "new");
+    cache.remove("a");
+    assert_eq!(cache.len(), 1);
+}
+
+#[tokio::test]
+async fn unconditional_not_modified_is_an_error() {
+    let (url, _) = serve(&[NOT_MODIFIED]);
+    let error = dataset(&url, Client::new())
+        .get(10, 0, Order::new(), None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, Error::Status { status, .. } if status == reqwest::StatusCode::NOT_MODIFIED),
+        "{error}"
+    );
+}
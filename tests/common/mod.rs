@@ -0,0 +1,66 @@
+//! Helpers shared by the tests, mainly a local HTTP server answering with fixed responses.
+#![allow(dead_code)]
+use basel::dynamic::{Dataset, Field};
+use basel::{Client, Portal};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+pub const OK: &str =
+    "200 OK\r\nContent-Type: application/json\r\n\r\n{\"total_count\": 0, \"results\": []}";
+
+/// Requests received so far, each as its lowercased request line and headers.
+#[derive(Clone, Default)]
+pub struct Requests(Arc<Mutex<Vec<String>>>);
+
+impl Requests {
+    pub fn count(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    pub fn all(&self) -> Vec<String> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn last(&self) -> String {
+        self.0.lock().unwrap().last().cloned().unwrap_or_default()
+    }
+}
+
+/// Serve `responses` one per connection, repeating the last one, and return the base URL.
+pub fn serve(responses: &'static [&'static str]) -> (String, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Requests::default();
+    let received = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+            while reader.read_line(&mut head).unwrap() > 0 && !head.ends_with("\r\n\r\n") {}
+            let n = {
+                let mut received = received.0.lock().unwrap();
+                received.push(head.to_lowercase());
+                received.len() - 1
+            };
+            let response = responses[n.min(responses.len() - 1)];
+            let (head, body) = response.split_once("\r\n\r\n").unwrap();
+            let response = format!(
+                "HTTP/1.1 {head}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+    (url, requests)
+}
+
+pub fn field(name: &str) -> Field {
+    Field::new(name)
+}
+
+/// Dataset 100089 of the Stadt portal, requested from `url` with `client`.
+pub fn dataset(url: &str, client: Client) -> Dataset {
+    Dataset::new(Portal::Stadt, "100089").client(&client.base_url(Portal::Stadt, url))
+}
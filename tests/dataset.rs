@@ -0,0 +1,474 @@
+use basel::stadt::belegung_der_elektroauto_ladestationen_der_iwb::{
+    self as ladestationen, fields, Dataset, Field,
+};
+use basel::{
+    catalog, dynamic, Aggregation, Client, Data, Dataset as _, Descriptor, Error, FacetValue,
+    Order, Portal, Refine, TypedField,
+};
+use common::{serve, OK};
+use futures_util::StreamExt;
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+mod common;
+
+const FACETS: &str = "200 OK\r\nContent-Type: application/json\r\n\r\n{\"facets\": [{\"name\": \"status\", \"facets\": [{\"name\": \"frei\", \"value\": \"frei\", \"count\": 9, \"state\": \"displayed\"}]}]}";
+const GROUPS: &str = "200 OK\r\nContent-Type: application/json\r\n\r\n{\"results\": [{\"status\": \"frei\", \"n\": 9}]}";
+
+#[tokio::test]
+async fn generic_methods() {
+    let (url, requests) = serve(&[FACETS, GROUPS, common::OK]);
+    let client = Client::new().base_url(Portal::Stadt, &url);
+
+    let facets = client
+        .facets::<Dataset>(Field::Status, Refine::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(facets[0].count, 9);
+    assert!(requests.last().contains("/datasets/100004/facets?"));
+
+    let aggregation = Aggregation::new().group_by(Field::Status).count("n");
+    let groups = client
+        .aggregate::<Dataset>(aggregation, Order::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(groups[0].get::<u64>("n"), Some(9));
+
+    let data = client
+        .select::<Dataset>(&[Field::Status], 10, 0, Order::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(data.total_count, 0);
+    assert!(requests.last().contains("select=%60status%60"));
+
+    client
+        .get_refined::<Dataset>(
+            10,
+            0,
+            Order::new(),
+            None,
+            Refine::new().refine(Field::Status, "frei"),
+        )
+        .await
+        .unwrap();
+    assert!(requests.last().contains("refine=status%3a%22frei%22"));
+}
+
+#[tokio::test]
+async fn base_urls() {
+    let (stadt, stadt_requests) = serve(&[OK]);
+    let (land, land_requests) = serve(&[OK]);
+    let client = Client::new()
+        .base_url(Portal::Stadt, &format!("{stadt}/"))
+        .base_url(Portal::Land, &land);
+    assert_eq!(client.portal_url(Portal::Stadt), stadt);
+    assert_eq!(
+        Client::new().portal_url(Portal::Land),
+        Portal::Land.default_base_url()
+    );
+
+    ladestationen::get_with(&client, 10, 0, Order::new(), None)
+        .await
+        .unwrap();
+    assert!(stadt_requests
+        .last()
+        .starts_with("get /api/explore/v2.1/catalog/datasets/100004/records?limit=10&offset=0 "));
+
+    basel::dynamic::Dataset::new(Portal::Land, "10010")
+        .client(&client)
+        .get(10, 0, Order::new(), None)
+        .await
+        .unwrap();
+    assert!(land_requests
+        .last()
+        .starts_with("get /api/explore/v2.1/catalog/datasets/10010/records?"));
+    assert_eq!((stadt_requests.count(), land_requests.count()), (1, 1));
+}
+
+#[tokio::test]
+async fn errors() {
+    const ODSQL: &str = "400 Bad Request\r\nContent-Type: application/json\r\n\r\n{\"error_code\": \"ODSQLError\", \"message\": \"Unknown field: foo\"}";
+    const PARAMETER: &str = "400 Bad Request\r\nContent-Type: application/json\r\n\r\n{\"error_code\": \"InvalidRESTParameterError\", \"message\": \"Invalid value for limit\"}";
+    const GATEWAY: &str =
+        "502 Bad Gateway\r\nContent-Type: text/html\r\n\r\n<html>Bad Gateway</html>";
+    const WRONG_TYPE: &str = "200 OK\r\nContent-Type: application/json\r\n\r\n{\"total_count\": 1, \"results\": [{\"parkingfield\": \"A1\"}]}";
+    let (url, _) = serve(&[ODSQL, PARAMETER, GATEWAY, WRONG_TYPE]);
+    let client = Client::new().base_url(Portal::Stadt, &url);
+    let get = || ladestationen::get_with(&client, 10, 0, Order::new(), None);
+
+    let error = get().await.unwrap_err();
+    assert!(
+        matches!(&error, Error::Query { error_code, message }
+            if error_code == "ODSQLError" && message == "Unknown field: foo"),
+        "{error}"
+    );
+    assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST));
+    assert_eq!(
+        error.to_string(),
+        "invalid query (ODSQLError): Unknown field: foo"
+    );
+
+    let error = get().await.unwrap_err();
+    assert!(
+        matches!(&error, Error::Status { status: StatusCode::BAD_REQUEST, error_code: Some(code), message: Some(_) }
+            if code == "InvalidRESTParameterError"),
+        "{error}"
+    );
+
+    let error = get().await.unwrap_err();
+    assert!(
+        matches!(
+            error,
+            Error::Status {
+                status: StatusCode::BAD_GATEWAY,
+                error_code: None,
+                message: None
+            }
+        ),
+        "{error}"
+    );
+
+    let error = get().await.unwrap_err();
+    assert!(
+        matches!(&error, Error::Deserialize { dataset, path, .. }
+            if dataset == "100004" && path == "results[0].parkingfield"),
+        "{error}"
+    );
+    assert!(std::error::Error::source(&error).is_some());
+
+    let invalid = Client::new().base_url(Portal::Stadt, "not a url");
+    let error = ladestationen::get_with(&invalid, 10, 0, Order::new(), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Url(_)), "{error}");
+}
+
+#[tokio::test]
+async fn jsonl_exports() {
+    const JSONL: &str = "200 OK\r\nContent-Type: application/jsonl\r\n\r\n{\"parkingfield\": 1}\n\n{\"parkingfield\": 2, \"status\": \"frei\"}\r\n{\"parkingfield\": \"A1\"}\n{\"parkingfield\": 3}";
+    let (url, requests) = serve(&[JSONL]);
+    let client = Client::new().base_url(Portal::Stadt, &url);
+
+    let records: Vec<_> =
+        ladestationen::export_with(&client, Order::new(), Some(fields::STATUS.equal("frei")))
+            .collect()
+            .await;
+    assert!(requests
+        .last()
+        .starts_with("get /api/explore/v2.1/catalog/datasets/100004/exports/jsonl?where="));
+    assert_eq!(records.len(), 4);
+    let parkingfield = |index: usize| records[index].as_ref().unwrap().parkingfield;
+    assert_eq!(
+        (parkingfield(0), parkingfield(1), parkingfield(3)),
+        (Some(1), Some(2), Some(3))
+    );
+    assert_eq!(records[1].as_ref().unwrap().status.as_deref(), Some("frei"));
+    assert!(
+        matches!(&records[2], Err(Error::Deserialize { path, .. }) if path == "parkingfield"),
+        "{:?}",
+        records[2]
+    );
+}
+
+#[tokio::test]
+async fn facets() {
+    const FACETS: &str = "200 OK\r\nContent-Type: application/json\r\n\r\n{\"links\": [], \"facets\": [
+        {\"name\": \"power\", \"facets\": [{\"name\": \"22 kW\", \"value\": \"22 kW\", \"count\": 12, \"state\": \"displayed\"}]},
+        {\"name\": \"status\", \"facets\": [
+            {\"name\": \"frei\", \"value\": \"frei\", \"count\": 9, \"state\": \"refined\"},
+            {\"name\": \"besetzt\", \"value\": \"besetzt\", \"count\": 0, \"state\": \"excluded\"}
+        ]}
+    ]}";
+    let (url, requests) = serve(&[FACETS]);
+    let client = Client::new().base_url(Portal::Stadt, &url);
+
+    let refine = Refine::new()
+        .refine(Field::Status, "frei")
+        .exclude(Field::Status, "besetzt");
+    let values = ladestationen::facets_with(
+        &client,
+        Field::Status,
+        refine,
+        Some(fields::PARKINGFIELD.greater(10)),
+    )
+    .await
+    .unwrap();
+    let request = requests.last();
+    assert!(request.starts_with("get /api/explore/v2.1/catalog/datasets/100004/facets?"));
+    assert!(request.contains("where=%60parkingfield%60+%3e+10&"));
+    assert!(request.contains("&refine=status%3a%22frei%22&exclude=status%3a%22besetzt%22&"));
+    assert!(request.contains("&facet=status "));
+    assert_eq!(
+        values,
+        [
+            FacetValue {
+                name: "frei".to_string(),
+                value: "frei".to_string(),
+                count: 9,
+                state: "refined".to_string(),
+            },
+            FacetValue {
+                name: "besetzt".to_string(),
+                value: "besetzt".to_string(),
+                count: 0,
+                state: "excluded".to_string(),
+            },
+        ]
+    );
+
+    let values = ladestationen::facets_with(&client, Field::Location, Refine::new(), None)
+        .await
+        .unwrap();
+    assert!(values.is_empty());
+}
+
+#[tokio::test]
+async fn metadata() {
+    const METADATA: &str = "200 OK\r\nContent-Type: application/json\r\n\r\n{
+        \"dataset_id\": \"100004\",
+        \"fields\": [
+            {\"name\": \"status\", \"label\": \"Status\", \"type\": \"text\", \"description\": null},
+            {\"name\": \"geo_point_2d\", \"label\": \"Koordinaten\", \"type\": \"geo_point_2d\"}
+        ],
+        \"metas\": {\"default\": {
+            \"title\": \"Belegung der Elektroauto-Ladestationen der IWB\",
+            \"description\": \"<p>Ladestationen</p>\",
+            \"modified\": \"2022-05-02T08:15:00+00:00\",
+            \"data_processed\": \"2022-05-02T08:20:31.5+00:00\",
+            \"metadata_processed\": null,
+            \"publisher\": \"IWB\",
+            \"license\": \"CC BY 4.0\",
+            \"license_url\": \"https://creativecommons.org/licenses/by/4.0/\",
+            \"keyword\": [\"Elektromobilität\", \"Laden\"],
+            \"theme\": null,
+            \"records_count\": 14
+        }}
+    }";
+    let (url, requests) = serve(&[METADATA]);
+    let client = Client::new().base_url(Portal::Stadt, &url);
+
+    let metadata = ladestationen::metadata_with(&client).await.unwrap();
+    assert!(requests
+        .last()
+        .starts_with("get /api/explore/v2.1/catalog/datasets/100004 "));
+    assert_eq!(metadata.dataset_id, "100004");
+    assert_eq!(metadata.title.as_deref(), Some(Dataset::TITLE));
+    assert_eq!(
+        metadata.modified,
+        Some(time::macros::datetime!(2022-05-02 08:15 UTC))
+    );
+    assert_eq!(
+        metadata.data_processed,
+        Some(time::macros::datetime!(2022-05-02 08:20:31.5 UTC))
+    );
+    assert_eq!(metadata.metadata_processed, None);
+    assert_eq!(metadata.publisher.as_deref(), Some("IWB"));
+    assert_eq!(metadata.keywords, ["Elektromobilität", "Laden"]);
+    assert!(metadata.themes.is_empty());
+    assert_eq!(metadata.records_count, Some(14));
+    let kinds: Vec<_> = metadata
+        .fields
+        .iter()
+        .map(|field| (field.name.as_str(), field.kind.as_str()))
+        .collect();
+    assert_eq!(
+        kinds,
+        [("status", "text"), ("geo_point_2d", "geo_point_2d")]
+    );
+    assert_eq!(metadata.fields[1].description, None);
+}
+
+#[tokio::test]
+async fn dynamic_datasets() {
+    const ROWS: &str = "200 OK\r\nContent-Type: application/json\r\n\r\n{\"total_count\": 31, \"results\": [{\"jahr\": 2024, \"gemeinde name\": \"Liestal\", \"anzahl\": 3}]}";
+    #[derive(Deserialize)]
+    struct Row {
+        jahr: i64,
+        anzahl: u32,
+    }
+
+    let (url, requests) = serve(&[ROWS]);
+    let client = Client::new().base_url(Portal::Land, &url);
+    let dataset = dynamic::Dataset::new(Portal::Land, "12345").client(&client);
+    assert_eq!((dataset.portal(), dataset.id()), (Portal::Land, "12345"));
+
+    let gemeinde = dynamic::Field::new("gemeinde name");
+    let order = Order::new().descending(dynamic::Field::new("jahr"));
+    let data: Data<Row> = dataset
+        .get_as(
+            5,
+            10,
+            order,
+            Some(TypedField::<_, String>::new(gemeinde).equal("Liestal")),
+        )
+        .await
+        .unwrap();
+    assert!(requests.last().starts_with(
+        "get /api/explore/v2.1/catalog/datasets/12345/records?where=%60gemeinde+name%60+%3d+%22liestal%22&order_by=%60jahr%60+desc&limit=5&offset=10 "
+    ));
+    assert_eq!(data.total_count, 31);
+    assert_eq!((data.results[0].jahr, data.results[0].anzahl), (2024, 3));
+
+    let data = dataset.get(5, 0, Order::new(), None).await.unwrap();
+    assert_eq!(data.results[0]["gemeinde name"], "Liestal");
+}
+
+#[test]
+fn registry() {
+    let descriptor = Descriptor::by_id(Portal::Stadt, "100004").unwrap();
+    assert_eq!(descriptor, Descriptor::of::<Dataset>());
+    assert_eq!(descriptor.feature, "bs100004");
+    assert_eq!(
+        descriptor.module,
+        "stadt::belegung_der_elektroauto_ladestationen_der_iwb"
+    );
+    assert_eq!(
+        descriptor.module_name(),
+        "belegung_der_elektroauto_ladestationen_der_iwb"
+    );
+    assert!(descriptor.fields.contains(&"geo_point_2d"));
+    assert_eq!(
+        Descriptor::by_module("belegung_der_elektroauto_ladestationen_der_iwb"),
+        Some(descriptor)
+    );
+    assert_eq!(Descriptor::by_module(descriptor.module), Some(descriptor));
+
+    assert_eq!(Descriptor::by_id(Portal::Land, "100004"), None);
+    assert_eq!(Descriptor::by_module("no_such_dataset"), None);
+
+    let registry = basel::registry();
+    assert!(registry.contains(&descriptor));
+    for enabled in registry {
+        assert_eq!(Descriptor::by_id(enabled.portal, enabled.id), Some(enabled));
+        assert_eq!(Descriptor::by_module(enabled.module), Some(enabled));
+    }
+}
+
+#[tokio::test]
+async fn catalog_hits() {
+    const CATALOG: &str =
+        "200 OK\r\nContent-Type: application/json\r\n\r\n{\"total_count\": 3, \"results\": [
+        {\"dataset_id\": \"100004\", \"metas\": {\"default\": {\"title\": \"Ladestationen\"}}},
+        {\"dataset_id\": \"100003\", \"metas\": {\"default\": {\"title\": \"Konsumentenpreise\"}}},
+        {\"dataset_id\": \"999999\", \"metas\": {\"default\": {}}}
+    ]}";
+    let (url, _) = serve(&[CATALOG]);
+    let client = Client::new().base_url(Portal::Stadt, &url);
+
+    let hits = catalog::search_with(&client, Portal::Stadt, &catalog::Search::new(), 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(hits.total_count, 3);
+    // Only known as a module if its feature is enabled, which it isn't by default.
+    let konsumentenpreise =
+        Descriptor::by_id(Portal::Stadt, "100003").map(|descriptor| descriptor.module);
+    let places: Vec<_> = hits
+        .results
+        .iter()
+        .map(|hit| (hit.portal, hit.feature, hit.module))
+        .collect();
+    assert_eq!(
+        places,
+        [
+            (
+                Portal::Stadt,
+                Some("bs100004"),
+                Some("stadt::belegung_der_elektroauto_ladestationen_der_iwb")
+            ),
+            (Portal::Stadt, Some("bs100003"), konsumentenpreise),
+            (Portal::Stadt, None, None),
+        ]
+    );
+    assert_eq!(
+        hits.results[1].metadata.title.as_deref(),
+        Some("Konsumentenpreise")
+    );
+}
+
+#[tokio::test]
+async fn catalog_queries() {
+    const STADT_FIRST: &str =
+        "200 OK\r\nContent-Type: application/json\r\n\r\n{\"total_count\": 3, \"results\": [
+        {\"dataset_id\": \"100004\", \"metas\": {\"default\": {}}},
+        {\"dataset_id\": \"100003\", \"metas\": {\"default\": {}}}
+    ]}";
+    const STADT_SECOND: &str =
+        "200 OK\r\nContent-Type: application/json\r\n\r\n{\"total_count\": 3, \"results\": [
+        {\"dataset_id\": \"100089\", \"metas\": {\"default\": {}}}
+    ]}";
+    const LAND: &str =
+        "200 OK\r\nContent-Type: application/json\r\n\r\n{\"total_count\": 1, \"results\": [
+        {\"dataset_id\": \"10010\", \"metas\": {\"default\": {}}}
+    ]}";
+    let (stadt_url, stadt_requests) = serve(&[STADT_FIRST, STADT_SECOND]);
+    let (land_url, land_requests) = serve(&[LAND]);
+    let client = Client::new()
+        .base_url(Portal::Stadt, &stadt_url)
+        .base_url(Portal::Land, &land_url);
+    let search = catalog::Search::new()
+        .text("Rhein")
+        .filter("records_count > 1000")
+        .theme("Umwelt")
+        .keyword("Pegel")
+        .publisher("Amt für Umwelt und Energie");
+
+    let hits = catalog::search_all_with(&client, &search).await.unwrap();
+    let found: Vec<_> = hits
+        .iter()
+        .map(|hit| (hit.portal, hit.metadata.dataset_id.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (Portal::Stadt, "100004"),
+            (Portal::Stadt, "100003"),
+            (Portal::Stadt, "100089"),
+            (Portal::Land, "10010"),
+        ]
+    );
+
+    let query = "?where=%28search%28%22rhein%22%29%29+and+%28records_count+%3e+1000%29\
+        &refine=theme%3a%22umwelt%22&refine=keyword%3a%22pegel%22\
+        &refine=publisher%3a%22amt+f%c3%bcr+umwelt+und+energie%22";
+    let pages: Vec<String> = stadt_requests
+        .all()
+        .iter()
+        .chain(&land_requests.all())
+        .map(|request| request.lines().next().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        pages,
+        [
+            format!("get /api/explore/v2.1/catalog/datasets{query}&limit=100&offset=0 http/1.1"),
+            format!("get /api/explore/v2.1/catalog/datasets{query}&limit=100&offset=2 http/1.1"),
+            format!("get /api/explore/v2.1/catalog/datasets{query}&limit=100&offset=0 http/1.1"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn dataset_ids() {
+    let (url, requests) = serve(&[OK]);
+    let client = Client::new().base_url(Portal::Stadt, &url);
+    for id in [
+        "",
+        "..",
+        "100004/records",
+        "100004?limit=1",
+        "100004#",
+        "a b",
+    ] {
+        let dataset = dynamic::Dataset::new(Portal::Stadt, id).client(&client);
+        let error = dataset.get(10, 0, Order::new(), None).await.unwrap_err();
+        assert!(matches!(error, Error::InvalidValue(_)), "{id}: {error}");
+        let error = dataset.metadata().await.unwrap_err();
+        assert!(matches!(error, Error::InvalidValue(_)), "{id}: {error}");
+    }
+    assert_eq!(requests.count(), 0);
+
+    let dataset = dynamic::Dataset::new(Portal::Stadt, "kantonale_wahlen-2020").client(&client);
+    dataset.get(10, 0, Order::new(), None).await.unwrap();
+    assert!(requests
+        .last()
+        .starts_with("get /api/explore/v2.1/catalog/datasets/kantonale_wahlen-2020/records?"));
+}
@@ -0,0 +1,102 @@
+use basel::dynamic::Dataset;
+use basel::testing::{FixtureMode, Fixtures};
+use basel::{Client, Error, Order, Portal};
+use common::{dataset, serve};
+use std::path::PathBuf;
+
+mod common;
+
+const RECORDS: &str = "200 OK\r\nContent-Type: application/json\r\n\r\n{\"total_count\": 2, \"results\": [{\"id\": 1}, {\"id\": 2}]}";
+
+fn temporary(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("basel-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[tokio::test]
+async fn records_then_replays() {
+    let dir = temporary("fixtures");
+    let (url, requests) = serve(&[RECORDS]);
+    let fixtures = Fixtures::new(&dir).mode(FixtureMode::Auto);
+
+    let recorded = dataset(&url, Client::new().fixtures(fixtures.clone()))
+        .get(2, 0, Order::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(recorded.total_count, 2);
+    assert_eq!(requests.count(), 1);
+
+    // Replayed without a server, whatever the base URL.
+    let offline = dataset(
+        "http://127.0.0.1:9",
+        Client::new().fixtures(fixtures.mode(FixtureMode::Replay)),
+    );
+    let replayed = offline.get(2, 0, Order::new(), None).await.unwrap();
+    assert_eq!(replayed.results, recorded.results);
+
+    // Another page is another request.
+    let error = offline.get(2, 2, Order::new(), None).await.unwrap_err();
+    assert!(matches!(error, Error::Fixture { ref source, .. }
+        if source.kind() == std::io::ErrorKind::NotFound));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn record_mode_overwrites() {
+    let dir = temporary("record");
+    let (url, requests) = serve(&[RECORDS]);
+    let fixtures = Fixtures::new(&dir).mode(FixtureMode::Record);
+    for _ in 0..2 {
+        dataset(&url, Client::new().fixtures(fixtures.clone()))
+            .get(2, 0, Order::new(), None)
+            .await
+            .unwrap();
+    }
+    assert_eq!(requests.count(), 2);
+    let files: Vec<_> = std::fs::read_dir(dir.join("stadt/100089"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(files.len(), 1);
+    assert!(files[0].starts_with("records-"), "{files:?}");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Fixtures are filed under the portal of the dataset, even if both portals share a base URL
+/// as with `MockServer::client`.
+#[tokio::test]
+async fn records_by_portal() {
+    let dir = temporary("portals");
+    let (url, _) = serve(&[RECORDS]);
+    let client = Client::new()
+        .base_url(Portal::Stadt, &url)
+        .base_url(Portal::Land, &url)
+        .fixtures(Fixtures::new(&dir).mode(FixtureMode::Record));
+    for (portal, id) in [(Portal::Stadt, "100089"), (Portal::Land, "10010")] {
+        Dataset::new(portal, id)
+            .client(&client)
+            .get(2, 0, Order::new(), None)
+            .await
+            .unwrap();
+    }
+    assert!(dir.join("stadt/100089").is_dir());
+    assert!(dir.join("land/10010").is_dir());
+    assert!(!dir.join("stadt/10010").exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Replays the synthetic fixture described in `tests/fixtures/README.md`.
+#[tokio::test]
+async fn replays_committed_fixture() {
+    use basel::stadt::belegung_der_elektroauto_ladestationen_der_iwb as ladestationen;
+
+    let fixtures = Fixtures::new("tests/fixtures").mode(FixtureMode::Replay);
+    let client = Client::new().fixtures(fixtures);
+    let data = ladestationen::get_with(&client, 2, 0, Order::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(data.total_count, 14);
+    assert_eq!(data.results.len(), 2);
+    assert_eq!(data.results[0].status.as_deref(), Some("frei"));
+}
@@ -0,0 +1,206 @@
+use basel::dynamic::Dataset;
+use basel::stadt::belegung_der_elektroauto_ladestationen_der_iwb as ladestationen;
+use basel::testing::MockServer;
+use basel::{
+    Error, ExportFormat, Expr, Filter, Order, Portal, RetryPolicy, TypedField, MAX_OFFSET,
+};
+use common::field;
+use futures_util::{StreamExt, TryStreamExt};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+mod common;
+
+fn server() -> MockServer {
+    let server = MockServer::start().unwrap();
+    server.load_dir("tests/data").unwrap();
+    server.dataset(
+        "100254",
+        (1..=25).map(|id| json!({ "id": id, "gerade": id % 2 == 0 })),
+    );
+    server
+}
+
+fn ids(records: &[Value]) -> Vec<i64> {
+    records
+        .iter()
+        .map(|record| record["id"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn pagination() {
+    let server = server();
+    let dataset = Dataset::new(Portal::Land, "100254").client(&server.client());
+
+    let page = dataset.get(10, 20, Order::new(), None).await.unwrap();
+    assert_eq!(page.total_count, 25);
+    assert_eq!(ids(&page.results), [21, 22, 23, 24, 25]);
+
+    let all: Vec<Value> = dataset
+        .stream(Order::new().descending(field("id")), None)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(ids(&all), (1..=25).rev().collect::<Vec<_>>());
+    assert_eq!(server.requests(), 1 + 1);
+}
+
+#[tokio::test]
+async fn offset_ceiling() {
+    let server = server();
+    server.dataset("100001", (0..=MAX_OFFSET).map(|id| json!({ "id": id })));
+    let dataset = Dataset::new(Portal::Stadt, "100001").client(&server.client());
+
+    let mut records = std::pin::pin!(dataset.stream::<Value>(Order::new(), None));
+    let error = records.next().await.unwrap().unwrap_err();
+    assert!(
+        matches!(error, Error::OffsetLimit { total_count } if total_count == MAX_OFFSET + 1),
+        "{error}"
+    );
+    assert!(records.next().await.is_none());
+    assert_eq!(server.requests(), 1);
+
+    // Taking only a few records doesn't help, the first item is still the error.
+    let first: Vec<Result<Value, Error>> =
+        dataset.stream(Order::new(), None).take(3).collect().await;
+    assert!(
+        matches!(first[..], [Err(Error::OffsetLimit { .. })]),
+        "{first:?}"
+    );
+    assert_eq!(server.requests(), 2);
+
+    let filter = TypedField::<_, i64>::new(field("id")).less(150);
+    let narrowed: Vec<Value> = dataset
+        .stream(Order::new(), Some(filter))
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(narrowed.len(), 150);
+}
+
+#[tokio::test]
+async fn filtering_and_ordering() {
+    let server = server();
+    let dataset = Dataset::new(Portal::Stadt, "100089").client(&server.client());
+
+    let filter = Expr::field(field("station"))
+        .lower()
+        .equal("basel st. johann")
+        .or(TypedField::<_, f64>::new(field("pegel")).greater(250.0));
+    let data = dataset
+        .get(10, 0, Order::new().ascending(field("datum")), Some(filter))
+        .await
+        .unwrap();
+    assert_eq!(data.total_count, 2);
+    assert_eq!(ids(&data.results), [2, 3]);
+    assert_eq!(data.results[1]["pegel"], Value::Null);
+
+    let nulls_first = dataset
+        .get(10, 0, Order::new().ascending(field("pegel")), None)
+        .await
+        .unwrap();
+    assert_eq!(ids(&nulls_first.results), [3, 1, 2]);
+
+    let selected = dataset
+        .select::<Value>(&[field("station")], 1, 0, Order::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(selected.results, [json!({ "station": "Basel Rheinhalle" })]);
+}
+
+#[tokio::test]
+async fn typed_select() {
+    let server = server();
+    server.dataset(
+        "100004",
+        [json!({ "parkingfield": 1, "status": "frei", "power": "22 kW", "location": "Claraplatz" })],
+    );
+    let data = ladestationen::select_with(
+        &server.client(),
+        &[ladestationen::Field::Status, ladestationen::Field::Power],
+        10,
+        0,
+        Order::new(),
+        None,
+    )
+    .await
+    .unwrap();
+    let record = &data.results[0];
+    assert_eq!(record.status.as_deref(), Some("frei"));
+    assert_eq!(record.power.as_deref(), Some("22 kW"));
+    assert_eq!(
+        (record.parkingfield, record.location.as_deref()),
+        (None, None)
+    );
+}
+
+#[tokio::test]
+async fn exports() {
+    let server = server();
+    let dataset = Dataset::new(Portal::Stadt, "100089").client(&server.client());
+
+    let records: Vec<Value> = dataset
+        .export(Order::new(), Some(Filter::is_null(field("pegel")).not()))
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(ids(&records), [1, 2]);
+
+    let csv: Vec<u8> = dataset
+        .export_bytes(
+            ExportFormat::Csv,
+            Order::new().descending(field("id")),
+            None,
+        )
+        .map(|chunk| chunk.unwrap().to_vec())
+        .concat()
+        .await;
+    let csv = String::from_utf8(csv).unwrap();
+    assert_eq!(csv.lines().next(), Some("datum;id;pegel;station"));
+    assert_eq!(csv.lines().nth(1), Some("2025-01-02;3;;basel St. Johann"));
+}
+
+#[tokio::test]
+async fn errors() {
+    let server = server();
+    let client = server.client();
+
+    let unknown = Dataset::new(Portal::Stadt, "999999").client(&client);
+    let error = unknown.get(10, 0, Order::new(), None).await.unwrap_err();
+    assert_eq!(error.status(), Some(reqwest::StatusCode::NOT_FOUND));
+
+    let dataset = Dataset::new(Portal::Stadt, "100089").client(&client);
+    let mismatch = Expr::literal("soon").year().equal(2025);
+    let error = dataset
+        .get(10, 0, Order::new(), Some(mismatch))
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Query { .. }), "{error}");
+
+    let error = dataset
+        .get(10, 9_995, Order::new(), None)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, Error::Status { ref error_code, .. }
+            if error_code.as_deref() == Some("InvalidRESTParameterError")),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn transient_failures_are_retried() {
+    let server = server();
+    let policy = RetryPolicy::default().base_delay(Duration::from_millis(5));
+    let dataset = Dataset::new(Portal::Stadt, "100089").client(&server.client().retry(policy));
+
+    server.fail(503, 2);
+    let data = dataset.get(10, 0, Order::new(), None).await.unwrap();
+    assert_eq!(data.total_count, 3);
+    assert_eq!(server.requests(), 3);
+
+    server.fail(429, 5);
+    let error = dataset.get(10, 0, Order::new(), None).await.unwrap_err();
+    assert_eq!(error.status(), Some(reqwest::StatusCode::TOO_MANY_REQUESTS));
+}
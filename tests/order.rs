@@ -0,0 +1,61 @@
+use basel::dynamic::Field;
+use basel::{Expr, GeoPoint2d, Order};
+use common::field;
+
+mod common;
+
+#[test]
+fn keys() {
+    let order = Order::new()
+        .descending(field("datum"))
+        .ascending(field("station"));
+    assert_eq!(order.to_string(), "`datum` desc, `station`");
+    assert_eq!(Order::<Field>::new().to_string(), "");
+}
+
+#[test]
+fn expressions() {
+    let mittlere_bruecke = GeoPoint2d {
+        lat: 47.5605,
+        lon: 7.5906,
+    };
+    let order = Order::new()
+        .ascending_by(Expr::distance(field("geo_point_2d"), mittlere_bruecke))
+        .descending_by(Expr::field(field("datum")).year());
+    assert_eq!(
+        order.to_string(),
+        "distance(`geo_point_2d`, geom'POINT(7.5906 47.5605)'), year(`datum`) desc"
+    );
+}
+
+#[test]
+fn aliases_and_random() {
+    let order = Order::<Field>::new()
+        .descending_alias("anzahl")
+        .ascending_alias("mittel `pegel`")
+        .random(42);
+    assert_eq!(
+        order.to_string(),
+        "`anzahl` desc, `mittel \\`pegel\\``, random(42)"
+    );
+}
+
+#[test]
+fn parsing() {
+    let normalized = |input: &str| Order::<Field>::parse(input).unwrap().to_string();
+    assert_eq!(normalized(""), "");
+    assert_eq!(
+        normalized("datum DESC, `station` asc"),
+        "`datum` desc, `station`"
+    );
+    assert_eq!(
+        normalized("year(datum) desc, random(7)"),
+        "year(`datum`) desc, random(7)"
+    );
+    assert_eq!(
+        Order::<Field>::parse("datum desc station")
+            .unwrap_err()
+            .span,
+        11..18
+    );
+}
@@ -0,0 +1,243 @@
+use basel::dynamic::Field;
+use basel::{Filter, ParseError, ParseErrorKind, TypedField, MAX_DEPTH};
+
+fn parse(input: &str) -> Result<Filter<Field>, ParseError> {
+    Filter::parse(input)
+}
+
+fn normalized(input: &str) -> String {
+    let filter = parse(input).unwrap();
+    let normalized = filter.to_string();
+    assert_eq!(parse(&normalized).unwrap().to_string(), normalized);
+    normalized
+}
+
+fn kind(input: &str) -> (ParseErrorKind, &str) {
+    let error = parse(input).unwrap_err();
+    (error.kind, &input[error.span])
+}
+
+#[test]
+fn round_trip() {
+    assert_eq!(normalized("station='Basel'"), "`station` = \"Basel\"");
+    assert_eq!(
+        normalized("pegel >= 245.5 AND NOT abfluss <> 800"),
+        "(`pegel` >= 245.5) and (not (`abfluss` != 800))"
+    );
+    assert_eq!(
+        normalized("a = 1 or b = 2 and c = 3"),
+        "(`a` = 1) or ((`b` = 2) and (`c` = 3))"
+    );
+    assert_eq!(normalized("not (pegel is null)"), "`pegel` is not null");
+    assert_eq!(
+        normalized("year(datum) = 2025 and timestamp >= now(days=-1, hours = 2)"),
+        "(year(`datum`) = 2025) and (`timestamp` >= now(days=-1, hours=2))"
+    );
+    assert_eq!(
+        normalized("datum < date'2025-01-01' and date_format(datum, 'yyyy-MM') = \"2024-12\""),
+        "(`datum` < date'2025-01-01') and (date_format(`datum`, \"yyyy-MM\") = \"2024-12\")"
+    );
+    assert_eq!(
+        normalized("lower(station) like 'basel%' or startswith(`station`, \"Rhein\")"),
+        "(lower(`station`) like \"basel%\") or (startswith(`station`, \"Rhein\"))"
+    );
+    assert_eq!(
+        normalized("within_distance(geo_point_2d, geom'POINT(7.59 47.56)', 1.5km)"),
+        "within_distance(`geo_point_2d`, geom'POINT(7.59 47.56)', 1500m)"
+    );
+    assert_eq!(
+        normalized("distance(geo_point_2d, geom'POINT(7.59 47.56)') < 500"),
+        "distance(`geo_point_2d`, geom'POINT(7.59 47.56)') < 500"
+    );
+    assert_eq!(
+        normalized("in_bbox(geo_point_2d, 47.5, 7.5, 47.6, 7.7)"),
+        "in_bbox(`geo_point_2d`, 47.5, 7.5, 47.6, 7.7)"
+    );
+    assert_eq!(
+        normalized("within(geo_shape, geom'POLYGON((7.5 47.5, 7.7 47.5, 7.7 47.6, 7.5 47.5))')"),
+        "within(`geo_shape`, geom'POLYGON((7.5 47.5, 7.7 47.5, 7.7 47.6, 7.5 47.5))')"
+    );
+    assert_eq!(
+        normalized("`0_bis_14jahrige_prozent` > -0.5"),
+        "`0_bis_14jahrige_prozent` > -0.5"
+    );
+}
+
+#[test]
+fn syntax_errors() {
+    assert_eq!(kind("station = "), (ParseErrorKind::UnexpectedEnd, ""));
+    assert_eq!(
+        kind("station = 'Basel"),
+        (ParseErrorKind::Unterminated, "'Basel")
+    );
+    assert_eq!(
+        kind("station == 'Basel'"),
+        (ParseErrorKind::UnexpectedToken("=".to_string()), "=")
+    );
+    assert_eq!(kind("(a = 1"), (ParseErrorKind::UnexpectedEnd, ""));
+    assert_eq!(
+        kind("a = 1 b = 2"),
+        (ParseErrorKind::UnexpectedToken("b".to_string()), "b")
+    );
+    assert_eq!(
+        kind("weekday(datum) = 1"),
+        (
+            ParseErrorKind::UnknownFunction("weekday".to_string()),
+            "weekday"
+        )
+    );
+    assert_eq!(
+        kind("datum = date'yesterday'"),
+        (
+            ParseErrorKind::InvalidLiteral("yesterday".to_string()),
+            "date'yesterday'"
+        )
+    );
+    assert_eq!(
+        kind("search('Rhein')"),
+        (
+            ParseErrorKind::Unsupported("search without a field"),
+            "search"
+        )
+    );
+}
+
+#[test]
+fn numbers() {
+    assert_eq!(normalized("a = 1e5"), "`a` = 100000");
+    assert_eq!(normalized("a > 2.5E-3"), "`a` > 0.0025");
+    assert_eq!(normalized("a < -1e+2"), "`a` < -100");
+    assert_eq!(
+        normalized("a = 9223372036854775807"),
+        "`a` = 9223372036854775807"
+    );
+    assert_eq!(
+        kind("a = 9223372036854775808"),
+        (
+            ParseErrorKind::InvalidLiteral("9223372036854775808".to_string()),
+            "9223372036854775808"
+        )
+    );
+    assert_eq!(normalized("a = 1e20"), "`a` = 1e20");
+    assert_eq!(
+        normalized("a = -9223372036854775808.0"),
+        "`a` = -9.223372036854776e18"
+    );
+    assert_eq!(
+        TypedField::<_, f64>::new(Field::new("a"))
+            .equal(1e20)
+            .to_string(),
+        "`a` = 1e20"
+    );
+    assert_eq!(
+        normalized("within_distance(geo_point_2d, geom'POINT(7.59 47.56)', 1e3m)"),
+        "within_distance(`geo_point_2d`, geom'POINT(7.59 47.56)', 1000m)"
+    );
+}
+
+#[test]
+fn nesting_depth() {
+    let nested = |depth| format!("{}a = 1{}", "(".repeat(depth), ")".repeat(depth));
+    assert!(parse(&nested(MAX_DEPTH)).is_ok());
+    let input = nested(100_000);
+    let error = parse(&input).unwrap_err();
+    assert_eq!(error.kind, ParseErrorKind::TooDeep);
+    assert_eq!(error.span, MAX_DEPTH..MAX_DEPTH + 1);
+
+    let negated = |depth| format!("{}a = 1", "not ".repeat(depth));
+    assert!(parse(&negated(MAX_DEPTH)).is_ok());
+    let input = negated(100_000);
+    assert_eq!(kind(&input), (ParseErrorKind::TooDeep, "not"));
+
+    let calls = |depth| format!("{}a{} = 'x'", "lower(".repeat(depth), ")".repeat(depth));
+    assert!(parse(&calls(MAX_DEPTH)).is_ok());
+    assert_eq!(kind(&calls(10_000)), (ParseErrorKind::TooDeep, "lower"));
+}
+
+#[test]
+fn long_chains() {
+    for terms in [150, 3_000, 20_000] {
+        let chain = |operator| {
+            (0..terms)
+                .map(|term| format!("a = {term}"))
+                .collect::<Vec<_>>()
+                .join(operator)
+        };
+        let all = normalized(&chain(" and "));
+        assert!(all.starts_with("(`a` = 0) and (`a` = 1) and "), "{all}");
+        let any = normalized(&chain(" OR "));
+        assert!(any.starts_with("(`a` = 0) or (`a` = 1) or "), "{any}");
+
+        let built = (0..terms)
+            .map(|term| TypedField::<_, i64>::new(Field::new("a")).equal(term))
+            .reduce(Filter::and)
+            .unwrap();
+        assert_eq!(built.to_string(), all);
+        assert_eq!(parse(&all), Ok(built));
+    }
+    assert_eq!(
+        normalized("a = 1 and (b = 2 and c = 3) or d = 4 or e = 5"),
+        "((`a` = 1) and (`b` = 2) and (`c` = 3)) or (`d` = 4) or (`e` = 5)"
+    );
+}
+
+#[test]
+fn type_errors() {
+    let mismatch = |expected, found| ParseErrorKind::TypeMismatch { expected, found };
+    assert_eq!(
+        kind("year('soon') = 2025"),
+        (mismatch("date", "text"), "'soon'")
+    );
+    assert_eq!(kind("lower(5) = 'a'"), (mismatch("text", "number"), "5"));
+    assert_eq!(
+        kind("year(datum) = 'x'"),
+        (mismatch("number", "text"), "'x'")
+    );
+    assert!(parse("year('2025-03-14') = 2025").is_ok());
+}
+
+#[test]
+fn generated_fields() {
+    use basel::stadt::belegung_der_elektroauto_ladestationen_der_iwb::Field;
+
+    let mismatch = |expected, found| ParseErrorKind::TypeMismatch { expected, found };
+    let kind = |input: &'static str| {
+        let error = Filter::<Field>::parse(input).unwrap_err();
+        (error.kind, &input[error.span])
+    };
+    assert!(Filter::<Field>::parse("status = 'frei' and totalparkings > 2").is_ok());
+    assert_eq!(
+        kind("stauts = 'frei'"),
+        (ParseErrorKind::UnknownField("stauts".to_string()), "stauts")
+    );
+    assert_eq!(
+        kind("totalparkings > 'viele'"),
+        (mismatch("number", "text"), "'viele'")
+    );
+    assert_eq!(
+        kind("startswith(parkingfield, '1')"),
+        (mismatch("text", "number"), "parkingfield")
+    );
+    assert_eq!(
+        kind("in_bbox(status, 47.5, 7.5, 47.6, 7.7)"),
+        (mismatch("geometry", "text"), "status")
+    );
+    assert_eq!(
+        kind("geo_point_2d = 1"),
+        (mismatch("text, number or date", "geometry"), "geo_point_2d")
+    );
+}
+
+#[test]
+fn generated_order() {
+    use basel::stadt::belegung_der_elektroauto_ladestationen_der_iwb::Field;
+    use basel::Order;
+
+    let order = Order::<Field>::parse("status, anzahl desc").unwrap();
+    let expected = Order::new()
+        .ascending(Field::Status)
+        .descending_alias("anzahl");
+    assert_eq!(order.to_string(), expected.to_string());
+    assert!(matches!(order.keys[0], basel::Sort::By(_, _)));
+    assert!(matches!(order.keys[1], basel::Sort::Alias(_, _)));
+}
@@ -0,0 +1,106 @@
+use basel::dynamic::Dataset;
+use basel::{Client, Error, Order, Portal, RateLimiter, RetryPolicy};
+use common::{dataset, serve, OK};
+use std::time::{Duration, Instant};
+
+mod common;
+
+fn quick() -> RetryPolicy {
+    RetryPolicy::default()
+        .base_delay(Duration::from_millis(10))
+        .max_delay(Duration::from_secs(1))
+}
+
+#[tokio::test]
+async fn retries_transient_errors() {
+    let (url, requests) = serve(&[
+        "503 Service Unavailable\r\n\r\n",
+        "502 Bad Gateway\r\n\r\n",
+        OK,
+    ]);
+    let data = dataset(&url, Client::new().retry(quick()))
+        .get(10, 0, Order::new(), None)
+        .await
+        .unwrap();
+    assert_eq!(data.total_count, 0);
+    assert_eq!(requests.count(), 3);
+}
+
+#[tokio::test]
+async fn gives_up_after_max_retries() {
+    let (url, requests) = serve(&["500 Internal Server Error\r\n\r\n"]);
+    let error = dataset(&url, Client::new().retry(quick().max_retries(2)))
+        .get(10, 0, Order::new(), None)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.status(),
+        Some(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+    );
+    assert_eq!(requests.count(), 3);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let (url, requests) = serve(&["404 Not Found\r\n\r\n{\"error_code\": \"NotFound\"}"]);
+    let error = dataset(&url, Client::new().retry(quick()))
+        .get(10, 0, Order::new(), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Status { .. }));
+    assert_eq!(requests.count(), 1);
+}
+
+#[tokio::test]
+async fn honours_retry_after() {
+    let (url, requests) = serve(&["429 Too Many Requests\r\nRetry-After: 1\r\n\r\n", OK]);
+    let start = Instant::now();
+    dataset(
+        &url,
+        Client::new().retry(quick().base_delay(Duration::ZERO)),
+    )
+    .get(10, 0, Order::new(), None)
+    .await
+    .unwrap();
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert_eq!(requests.count(), 2);
+
+    let (url, requests) = serve(&["429 Too Many Requests\r\nRetry-After: 3600\r\n\r\n", OK]);
+    let error = dataset(&url, Client::new().retry(quick()))
+        .get(10, 0, Order::new(), None)
+        .await
+        .unwrap_err();
+    assert_eq!(error.status(), Some(reqwest::StatusCode::TOO_MANY_REQUESTS));
+    assert_eq!(requests.count(), 1);
+}
+
+#[tokio::test]
+async fn rate_limiter_is_shared() {
+    let (url, requests) = serve(&[OK]);
+    let limiter = RateLimiter::with_burst(10, Duration::from_secs(1), 1);
+    let stadt = dataset(&url, Client::new().rate_limit(limiter.clone()));
+    let land = Client::new()
+        .base_url(Portal::Land, &url)
+        .rate_limit(limiter);
+    let land = Dataset::new(Portal::Land, "10010").client(&land);
+    let start = Instant::now();
+    for dataset in [&stadt, &land, &stadt] {
+        dataset.get(10, 0, Order::new(), None).await.unwrap();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert_eq!(requests.count(), 3);
+}
+
+#[test]
+fn backoff_grows_up_to_max_delay() {
+    let policy = RetryPolicy::default()
+        .jitter(false)
+        .base_delay(Duration::from_millis(100))
+        .max_delay(Duration::from_millis(500));
+    let delays: Vec<_> = (0..4)
+        .map(|retry| policy.backoff(retry).as_millis())
+        .collect();
+    assert_eq!(delays, [100, 200, 400, 500]);
+    let jittered = policy.jitter(true).backoff(10);
+    assert!(jittered <= Duration::from_millis(500));
+}